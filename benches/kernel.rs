@@ -0,0 +1,46 @@
+//Escape-time kernels on the default 800x600 initial view
+//run with:  cargo bench --bench kernel
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use num::Complex;
+use std::hint::black_box;
+
+use orbits::complex_bbox::ComplexBBox;
+use orbits::kernel::{render_iterations, Kernel};
+use orbits::subdivide;
+
+fn initial_view() -> ComplexBBox {
+    let j = Complex { re: 0.0, im: 1.0 };
+    ComplexBBox {
+        ll: -1.5 - j,
+        ur: 0.5 + j,
+    }
+}
+
+fn kernels(c: &mut Criterion) {
+    let view = initial_view();
+    let mut group = c.benchmark_group("initial_view_800x600");
+    group.sample_size(20);
+
+    for iterations in [50, 500] {
+        for kernel in Kernel::ALL {
+            group.bench_with_input(
+                BenchmarkId::new(kernel.name(), iterations),
+                &iterations,
+                |b, &iterations| {
+                    b.iter(|| render_iterations(black_box(&view), 800, 600, iterations, kernel))
+                },
+            );
+        }
+        group.bench_with_input(
+            BenchmarkId::new("subdivide", iterations),
+            &iterations,
+            |b, &iterations| {
+                b.iter(|| subdivide::render_iterations(black_box(&view), 800, 600, iterations))
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kernels);
+criterion_main!(benches);
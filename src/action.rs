@@ -0,0 +1,164 @@
+//Everything the viewer can be asked to do, by a key, the menu, a script or
+//the control socket.  keymap.rs binds keys and mouse buttons to them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ToggleCoords,
+    TogglePerfHud,
+    MoreIterations,
+    FewerIterations,
+    ToggleAutoIterations,
+    NextAntiAlias,
+    NextLineThickness,
+    ToggleLineAntiAlias,
+    ToggleMarkers,
+    NextNumbering,
+    ToggleGradient,
+    TogglePlayback,
+    TogglePause,
+    StepForward,
+    StepBack,
+    Faster,
+    Slower,
+    NextKernel,
+    NextStrategy,
+    ToggleFullscreen,
+    ExitFullscreen,
+    Home,
+    ZoomIn,
+    ZoomOut,
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    ToggleMenu,
+    SaveOrbit,
+    ClearOrbits,
+    ExportCsv,
+    ExportJson,
+    ExportSvg,
+    ExportSvgWithAxes,
+    Screenshot,
+    ScreenshotWithoutHud,
+    ExportLocation,
+    ZoomSequence,
+    ZoomSequenceWithOrbit,
+    ToggleConsole,
+    CommandLine,
+    About,
+}
+
+impl Action {
+    pub const ALL: [Action; 44] = [
+        Action::Quit,
+        Action::ToggleCoords,
+        Action::TogglePerfHud,
+        Action::MoreIterations,
+        Action::FewerIterations,
+        Action::ToggleAutoIterations,
+        Action::NextAntiAlias,
+        Action::NextLineThickness,
+        Action::ToggleLineAntiAlias,
+        Action::ToggleMarkers,
+        Action::NextNumbering,
+        Action::ToggleGradient,
+        Action::TogglePlayback,
+        Action::TogglePause,
+        Action::StepForward,
+        Action::StepBack,
+        Action::Faster,
+        Action::Slower,
+        Action::NextKernel,
+        Action::NextStrategy,
+        Action::ToggleFullscreen,
+        Action::ExitFullscreen,
+        Action::Home,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::ToggleMenu,
+        Action::SaveOrbit,
+        Action::ClearOrbits,
+        Action::ExportCsv,
+        Action::ExportJson,
+        Action::ExportSvg,
+        Action::ExportSvgWithAxes,
+        Action::Screenshot,
+        Action::ScreenshotWithoutHud,
+        Action::ExportLocation,
+        Action::ZoomSequence,
+        Action::ZoomSequenceWithOrbit,
+        Action::ToggleConsole,
+        Action::CommandLine,
+        Action::About,
+    ];
+
+    //as written in the [keys] section of the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::ToggleCoords => "coordinates",
+            Action::TogglePerfHud => "perf_hud",
+            Action::MoreIterations => "more_iterations",
+            Action::FewerIterations => "fewer_iterations",
+            Action::ToggleAutoIterations => "auto_iterations",
+            Action::NextAntiAlias => "antialias",
+            Action::NextLineThickness => "line_thickness",
+            Action::ToggleLineAntiAlias => "line_antialias",
+            Action::ToggleMarkers => "markers",
+            Action::NextNumbering => "numbering",
+            Action::ToggleGradient => "gradient",
+            Action::TogglePlayback => "playback",
+            Action::TogglePause => "pause",
+            Action::StepForward => "step_forward",
+            Action::StepBack => "step_back",
+            Action::Faster => "faster",
+            Action::Slower => "slower",
+            Action::NextKernel => "kernel",
+            Action::NextStrategy => "strategy",
+            Action::ToggleFullscreen => "fullscreen",
+            Action::ExitFullscreen => "exit_fullscreen",
+            Action::Home => "home",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::CursorUp => "cursor_up",
+            Action::CursorDown => "cursor_down",
+            Action::ToggleMenu => "menu",
+            Action::SaveOrbit => "save_orbit",
+            Action::ClearOrbits => "clear_orbits",
+            Action::ExportCsv => "export_csv",
+            Action::ExportJson => "export_json",
+            Action::ExportSvg => "export_svg",
+            Action::ExportSvgWithAxes => "export_svg_axes",
+            Action::Screenshot => "screenshot",
+            Action::ScreenshotWithoutHud => "screenshot_without_hud",
+            Action::ExportLocation => "export_location",
+            Action::ZoomSequence => "zoom_sequence",
+            Action::ZoomSequenceWithOrbit => "zoom_sequence_orbit",
+            Action::ToggleConsole => "console",
+            Action::CommandLine => "command_line",
+            Action::About => "about",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Action, String> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|a| a.name() == name)
+            .ok_or(format!(
+                "unknown action \"{}\", expected one of: {}",
+                name,
+                Action::ALL
+                    .iter()
+                    .map(|a| a.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+    }
+}
@@ -0,0 +1,901 @@
+//The viewer's state and how input changes it, kept apart from SDL so it can be
+//tested on its own.  main turns events into calls here and carries out the
+//Effects: rendering the background, changing the window and writing files.
+use log::{debug, info, trace};
+use num::Complex;
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::action::Action;
+use crate::auto_iterations;
+use crate::command_line::{self, Entry};
+use crate::complex_bbox::{self, ComplexBBox};
+use crate::console::Console;
+use crate::geometry::{Point, Rect};
+use crate::location;
+use crate::orbit::{calc_orbits, Bailout};
+use crate::orbit_style::OrbitStyle;
+use crate::playback::Playback;
+use crate::render::RenderSettings;
+use crate::screenshot::{self, ViewMetadata};
+use crate::zoom_sequence;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Other, //only the left one pans, the others are bound to actions in keymap.rs
+}
+
+//The keys that edit a line being typed, the rest is text_input
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EditKey {
+    Enter,
+    Backspace,
+    Escape,
+}
+
+//Work that needs more than the state, left to main
+#[derive(Clone, Debug, PartialEq)]
+pub enum Task {
+    ExportOrbits {
+        c: Complex<f64>,
+        json: bool,
+    },
+    ExportLocation,
+    ZoomSequence {
+        c: Complex<f64>,
+        with_orbit: bool,
+        frames: usize,
+        depth: f64, //magnification of the last frame
+    },
+    ExportSvg {
+        c: Complex<f64>,
+        axes: bool,
+    },
+    Screenshot {
+        hud: bool,
+    },
+    About,
+    RunScript(String), //typed into the console
+    CancelScripts,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Effects {
+    pub quit: bool,
+    pub render: bool, //the background is out of date
+    pub fullscreen: Option<bool>,
+    pub warp_mouse: Option<Point>,
+    pub text_input: Option<bool>, //SDL's text input events on or off
+    pub task: Option<Task>,
+}
+
+impl Effects {
+    fn render() -> Effects {
+        Effects {
+            render: true,
+            ..Effects::default()
+        }
+    }
+
+    //both, for an event that also triggers an action
+    pub fn and(self, later: Effects) -> Effects {
+        Effects {
+            quit: self.quit || later.quit,
+            render: self.render || later.render,
+            fullscreen: later.fullscreen.or(self.fullscreen),
+            warp_mouse: later.warp_mouse.or(self.warp_mouse),
+            text_input: later.text_input.or(self.text_input),
+            task: later.task.or(self.task),
+        }
+    }
+}
+
+pub struct App {
+    pub view: ComplexBBox,
+    pub initial_view: ComplexBBox, //for Home
+    pub settings: RenderSettings,
+    pub initial_iterations: u32,
+    pub size: (u32, u32), //of the window
+    //part of the background texture shown and where, stretched while pinching
+    //and moved while panning until the next render
+    pub bg_rect_src: Rect,
+    pub bg_rect_dest: Rect,
+    pub drag: (i32, i32), //pan so far
+    pub mouse: Point,
+    pub position: Complex<f64>, //under the mouse, for the coordinates display
+    pub display_menu_q: bool,
+    pub show_coords_q: bool,
+    pub show_perf_q: bool,
+    pub touch_zoom: Option<Point>, //pinch in progress, about this point
+    pub saved_orbits: Vec<Complex<f64>>, //c of each saved orbit
+    pub full_screen: bool,
+    pub orbit_style: OrbitStyle,
+    pub playback: Option<Playback>,
+    pub bailout: Bailout,
+    pub console: Console,
+    pub running_scripts: usize, //started and not yet done, see Task::RunScript
+    pub command_line: Option<String>, //being typed after `:`
+    pub message: Option<String>, //what went wrong with the last command, for the HUD
+    pub status: Option<String>, //of work in the background, for the HUD
+    pub zoom_frames: usize,     //offered by the zoom sequence prompt
+    pub zoom_depth: f64,
+}
+
+impl App {
+    pub fn new(view: ComplexBBox, iterations: u32, size: (u32, u32)) -> App {
+        App {
+            view,
+            initial_view: view,
+            settings: RenderSettings::new(iterations),
+            initial_iterations: iterations,
+            size,
+            bg_rect_src: Rect::new(0, 0, size.0, size.1),
+            bg_rect_dest: Rect::new(0, 0, size.0, size.1),
+            drag: (0, 0),
+            mouse: Point::new(0, 0),
+            position: Complex::new(0.0, 0.0),
+            display_menu_q: false,
+            show_coords_q: true,
+            show_perf_q: false,
+            touch_zoom: None,
+            saved_orbits: Vec::new(),
+            full_screen: false,
+            orbit_style: OrbitStyle::default(),
+            playback: None,
+            bailout: Bailout::default(),
+            console: Console::default(),
+            running_scripts: 0,
+            command_line: None,
+            message: None,
+            status: None,
+            zoom_frames: zoom_sequence::FRAMES,
+            zoom_depth: zoom_sequence::DEPTH,
+        }
+    }
+
+    fn window_rect(&self) -> Rect {
+        Rect::new(0, 0, self.size.0, self.size.1)
+    }
+
+    fn screen_to_complex(&self, x: i32, y: i32) -> Complex<f64> {
+        let (w, h) = (self.size.0 as i32, self.size.1 as i32);
+        self.view.screen_to_complex(x, y, w, h)
+    }
+
+    //c under the mouse
+    pub fn mouse_c(&self) -> Complex<f64> {
+        self.screen_to_complex(self.mouse.x(), self.mouse.y())
+    }
+
+    //The orbit being looked at, the one played back or else under the mouse
+    pub fn seed(&self) -> Complex<f64> {
+        match &self.playback {
+            Some(p) => p.seed,
+            None => self.mouse_c(),
+        }
+    }
+
+    //Left button down outside the menu (picking from it is an action)
+    pub fn press(&mut self) -> Effects {
+        if !self.display_menu_q {
+            self.drag = (0, 0);
+        }
+        self.display_menu_q = false;
+        Effects::default()
+    }
+
+    //A button let go, the left one finishes dragging/panning
+    pub fn release(&mut self, button: MouseButton) -> Effects {
+        self.display_menu_q = false;
+        if button != MouseButton::Left {
+            return Effects::default();
+        }
+        let (w, h) = (self.size.0 as i32, self.size.1 as i32);
+        let shift = self.view.complex_deltas(w, h, self.drag.0, self.drag.1);
+        self.view = ComplexBBox {
+            ll: self.view.ll - shift,
+            ur: self.view.ur - shift,
+        };
+        self.bg_rect_dest = self.window_rect();
+        self.drag = (0, 0);
+        Effects::render()
+    }
+
+    //Mouse moved by (xrel,yrel) to (x,y), panning if `left` is held
+    pub fn motion(&mut self, x: i32, y: i32, xrel: i32, yrel: i32, left: bool) -> Effects {
+        if left {
+            //TODO: Problem with emscripten thinking that left mouse button is pressed after return from full screen mode
+            trace!(target: "events", "left pressed...");
+            self.drag = (self.drag.0 + xrel, self.drag.1 + yrel);
+            let dest = &mut self.bg_rect_dest;
+            dest.set_x(dest.x() + xrel);
+            dest.set_y(dest.y() + yrel);
+        } else {
+            self.position = self.screen_to_complex(x, y);
+        }
+        Effects::default()
+    }
+
+    //Two finger pinch at (x,y), as fractions of the window.  The background is
+    //stretched until the fingers lift, then rendered again.
+    pub fn pinch(&mut self, x: f32, y: f32, d_dist: f32, num_fingers: u16) -> Effects {
+        if num_fingers != 2 {
+            debug!(target: "events", "Multi-touch num_fingers: {}", num_fingers);
+            return Effects::default();
+        }
+        let (width, height) = self.size;
+        let x = (x * width as f32).floor() as i32;
+        let y = (y * height as f32).floor() as i32;
+        self.touch_zoom = Some(Point::new(x, y));
+        //todo also do panning with two fingers
+        //fix to only adjust one rect if the other hasn't yet been modified.  i.e. pinch zoom isn't finished yet
+        if d_dist > 0.0 {
+            //zoom in
+            let src = &mut self.bg_rect_src;
+            let new_width = ((src.width() as f32) * (1.0 - 10.0 * d_dist)) as u32;
+            let new_height = ((src.height() as f32) * (1.0 - 10.0 * d_dist)) as u32;
+            src.set_width(new_width);
+            src.set_height(new_height);
+            src.center_on(Point::new(x, y));
+            self.bg_rect_dest = self.window_rect(); //maybe don't reset dest?
+        } else {
+            //zoom out, remember d_dist is negative here
+            self.bg_rect_src = self.window_rect(); //maybe don't reset src?
+            let dest = &mut self.bg_rect_dest;
+            let new_width = ((dest.width() as f32) * (1.0 + 10.0 * d_dist)) as u32;
+            let new_height = ((dest.height() as f32) * (1.0 + 10.0 * d_dist)) as u32;
+            dest.set_width(new_width);
+            dest.set_height(new_height);
+            dest.center_on(Point::new(x, y));
+        }
+        Effects::default()
+    }
+
+    pub fn finger_up(&mut self) -> Effects {
+        match self.touch_zoom.take() {
+            Some(pos) => {
+                let complex_pos = self.screen_to_complex(pos.x(), pos.y());
+                let zoomies =
+                    (self.bg_rect_src.width() as f64) / (self.bg_rect_dest.width() as f64);
+                self.view = self.view.zoom(complex_pos, zoomies);
+                self.bg_rect_dest = self.window_rect();
+                self.bg_rect_src = self.window_rect();
+                Effects::render()
+            }
+            None => Effects::default(),
+        }
+    }
+
+    //The window is now `size`, and needs a background to match
+    pub fn resized(&mut self, size: (u32, u32)) -> Effects {
+        self.size = size;
+        self.bg_rect_src = self.window_rect();
+        self.bg_rect_dest = self.window_rect();
+        Effects::render()
+    }
+
+    pub fn set_view(&mut self, view: ComplexBBox) -> Effects {
+        self.view = view;
+        self.bg_rect_src = self.window_rect();
+        self.bg_rect_dest = self.window_rect();
+        Effects::render()
+    }
+
+    //A fixed limit, taking over from the automatic one
+    //callers check the limit, with their own way of saying what's wrong
+    pub fn set_iterations(&mut self, iterations: u32) -> Effects {
+        debug_assert!((1..=auto_iterations::MAX_ITERATIONS).contains(&iterations));
+        self.settings.auto_iterations = false;
+        self.settings.iterations = iterations;
+        Effects::render()
+    }
+
+    //Go back to a view from a screenshot or location file
+    pub fn restore(
+        &mut self,
+        view: ComplexBBox,
+        iterations: u32,
+        saved_orbits: Vec<Complex<f64>>,
+    ) -> Effects {
+        debug_assert!(complex_bbox::bbox(view.ll, view.ur).is_ok());
+        debug_assert!(saved_orbits
+            .iter()
+            .all(|c| c.re.is_finite() && c.im.is_finite()));
+        self.saved_orbits = saved_orbits;
+        self.set_iterations(iterations);
+        self.set_view(view)
+    }
+
+    //Restore the view in a screenshot, or a location file (which keeps the
+    //saved orbits), for dropped files, --open and the open command
+    pub fn open(&mut self, path: &Path) -> Result<Effects, String> {
+        let restored = match location::Format::from_path(path) {
+            Some(format) => std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| {
+                    let aspect = self.size.0 as f64 / self.size.1 as f64;
+                    location::read(format, &text, aspect)
+                })
+                .map(|l| (l.view, l.iterations, self.saved_orbits.clone())),
+            None => std::fs::read(path)
+                .map_err(|e| e.to_string())
+                .and_then(|png| screenshot::decode(&png))
+                .map(|m| (m.view, m.iterations, m.saved_orbits)),
+        };
+        let (view, iterations, saved_orbits) =
+            restored.map_err(|e| format!("{}: {}", path.display(), e))?;
+        info!("Restored view from {}", path.display());
+        Ok(self.restore(view, iterations, saved_orbits))
+    }
+
+    //What screenshots record
+    pub fn metadata(&self) -> ViewMetadata {
+        ViewMetadata {
+            view: self.view,
+            iterations: self.settings.iterations,
+            saved_orbits: self.saved_orbits.clone(),
+        }
+    }
+
+    //Where the viewer is, for --dump-state and the control socket
+    pub fn state(&self) -> Value {
+        let pair = |c: Complex<f64>| [c.re, c.im];
+        json!({
+            "view": { "ll": pair(self.view.ll), "ur": pair(self.view.ur) },
+            "iterations": self.settings.iterations,
+            "saved_orbits": self.saved_orbits.iter().map(|c| pair(*c)).collect::<Vec<_>>(),
+        })
+    }
+
+    //Any key let go closes the menu, except the one that opened it
+    pub fn key_up(&mut self, action: Option<Action>) -> Effects {
+        if action != Some(Action::ToggleMenu) {
+            self.display_menu_q = false;
+        }
+        Effects::default()
+    }
+
+    //Typing into the command line or console, rather than using the keys
+    pub fn typing(&self) -> bool {
+        self.command_line.is_some() || self.console.open
+    }
+
+    //Keys go to the command line or console while one is open, the typing
+    //itself comes as text_input
+    pub fn typing_key(&mut self, key: EditKey) -> Effects {
+        if let Some(line) = self.command_line.as_mut() {
+            return match key {
+                EditKey::Enter => {
+                    let line = self.command_line.take().unwrap_or_default();
+                    let effects = Effects {
+                        text_input: Some(self.console.open),
+                        ..Effects::default()
+                    };
+                    effects.and(self.run_command(&line))
+                }
+                //backspacing past the start leaves, like the escape key
+                EditKey::Backspace if !line.is_empty() => {
+                    line.pop();
+                    Effects::default()
+                }
+                EditKey::Backspace | EditKey::Escape => {
+                    self.command_line = None;
+                    Effects {
+                        text_input: Some(self.console.open),
+                        ..Effects::default()
+                    }
+                }
+            };
+        }
+        match key {
+            EditKey::Enter => match self.console.submit() {
+                Some(source) => {
+                    self.running_scripts += 1;
+                    Effects {
+                        task: Some(Task::RunScript(source)),
+                        ..Effects::default()
+                    }
+                }
+                None => Effects::default(),
+            },
+            EditKey::Backspace => {
+                self.console.backspace();
+                Effects::default()
+            }
+            //stop a script that's running too long before closing
+            EditKey::Escape if self.running_scripts > 0 => Effects {
+                task: Some(Task::CancelScripts),
+                ..Effects::default()
+            },
+            EditKey::Escape => self.handle(Action::ToggleConsole),
+        }
+    }
+
+    pub fn text_input(&mut self, text: &str) {
+        if let Some(line) = self.command_line.as_mut() {
+            line.push_str(text);
+        } else if self.console.open {
+            self.console.type_text(text);
+        }
+    }
+
+    //A line from the command line or stdin, see command_line.rs.  What's
+    //wrong with it is left in the HUD.
+    pub fn run_command(&mut self, line: &str) -> Effects {
+        let entry = match command_line::parse(line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                info!(target: "command", "{}: {}", line.trim(), e);
+                self.message = Some(e);
+                return Effects::default();
+            }
+        };
+        self.message = None;
+        match entry {
+            Entry::Goto { center, width } => {
+                let size = self.view.ur - self.view.ll;
+                let size = match width {
+                    Some(width) => size * (width / size.re), //the same shape
+                    None => size,
+                };
+                self.set_view(ComplexBBox {
+                    ll: center - size / 2.0,
+                    ur: center + size / 2.0,
+                })
+            }
+            Entry::Orbit(c) => {
+                self.saved_orbits.push(c);
+                Effects::default()
+            }
+            Entry::Iterations(iterations) => self.set_iterations(iterations),
+            Entry::Open(path) => match self.open(Path::new(&path)) {
+                Ok(effects) => effects,
+                Err(e) => {
+                    info!(target: "command", "{}: {}", line.trim(), e);
+                    self.message = Some(format!("couldn't open {}", e));
+                    Effects::default()
+                }
+            },
+            Entry::Radius(radius) => {
+                self.bailout.radius = radius;
+                //a smaller radius can end the played orbit sooner
+                if let Some(p) = self.playback.as_mut() {
+                    p.fit(calc_orbits(p.seed, &self.bailout).len());
+                }
+                Effects::default()
+            }
+            Entry::Zoom {
+                c,
+                frames,
+                depth,
+                with_orbit,
+            } => {
+                //offered again next time
+                self.zoom_frames = frames.unwrap_or(self.zoom_frames);
+                self.zoom_depth = depth.unwrap_or(self.zoom_depth);
+                Effects {
+                    task: Some(Task::ZoomSequence {
+                        c,
+                        with_orbit,
+                        frames: self.zoom_frames,
+                        depth: self.zoom_depth,
+                    }),
+                    ..Effects::default()
+                }
+            }
+        }
+    }
+
+    pub fn handle(&mut self, action: Action) -> Effects {
+        let mut effects = Effects::default();
+        match action {
+            Action::Quit => effects.quit = true,
+            Action::ToggleCoords => {
+                self.show_coords_q = !self.show_coords_q;
+            }
+            Action::TogglePerfHud => {
+                self.show_perf_q = !self.show_perf_q;
+            }
+            Action::MoreIterations | Action::FewerIterations => {
+                //manual changes take over from the automatic limit
+                self.settings.auto_iterations = false;
+                if action == Action::FewerIterations {
+                    self.settings.iterations = (self.settings.iterations / 2).max(1);
+                } else {
+                    self.settings.iterations = self
+                        .settings
+                        .iterations
+                        .saturating_mul(2)
+                        .min(auto_iterations::MAX_ITERATIONS);
+                }
+                effects.render = true;
+            }
+            Action::ToggleAutoIterations => {
+                //turning it off keeps the limit it had got to
+                self.settings.auto_iterations = !self.settings.auto_iterations;
+                effects.render = true;
+            }
+            Action::NextAntiAlias => {
+                self.settings.antialias = self.settings.antialias.next();
+                info!(target: "render", "anti-aliasing: {}", self.settings.antialias.name());
+                effects.render = true;
+            }
+            Action::NextLineThickness => {
+                self.orbit_style.next_thickness();
+            }
+            Action::ToggleLineAntiAlias => {
+                self.orbit_style.antialias = !self.orbit_style.antialias;
+            }
+            Action::ToggleMarkers => {
+                self.orbit_style.markers = !self.orbit_style.markers;
+            }
+            Action::NextNumbering => {
+                self.orbit_style.next_numbered();
+            }
+            Action::ToggleGradient => {
+                self.orbit_style.gradient = !self.orbit_style.gradient;
+            }
+            Action::TogglePlayback => {
+                self.playback = match self.playback {
+                    Some(_) => None,
+                    None => Some(Playback::new(self.mouse_c())),
+                };
+            }
+            Action::TogglePause
+            | Action::StepForward
+            | Action::StepBack
+            | Action::Faster
+            | Action::Slower => {
+                if let Some(p) = self.playback.as_mut() {
+                    let len = calc_orbits(p.seed, &self.bailout).len();
+                    match action {
+                        Action::TogglePause => p.toggle_pause(len),
+                        Action::StepForward => p.step_forward(len),
+                        Action::StepBack => p.step_back(),
+                        Action::Faster => p.faster(),
+                        _ => p.slower(),
+                    }
+                }
+            }
+            Action::NextKernel => {
+                self.settings.kernel = self.settings.kernel.next();
+                info!(target: "render", "kernel: {}", self.settings.kernel.name());
+                effects.render = true;
+            }
+            Action::NextStrategy => {
+                self.settings.strategy = self.settings.strategy.next();
+                info!(target: "render", "render strategy: {}", self.settings.strategy.name());
+                effects.render = true;
+            }
+            Action::ToggleFullscreen => {
+                debug!(target: "events", "full_screen:{}", self.full_screen);
+                self.full_screen = !self.full_screen;
+                effects.fullscreen = Some(self.full_screen);
+            }
+            Action::ExitFullscreen => {
+                if self.full_screen {
+                    self.full_screen = false;
+                    effects.fullscreen = Some(false);
+                }
+            }
+            Action::Home => {
+                self.view = self.initial_view;
+                self.settings.iterations = self.initial_iterations;
+                self.bg_rect_src = self.window_rect();
+                self.bg_rect_dest = self.window_rect();
+                effects.render = true;
+            }
+            Action::ZoomIn | Action::ZoomOut => {
+                let zoomies = if action == Action::ZoomIn { 0.5 } else { 2.0 };
+                self.view = self.view.zoom(self.mouse_c(), zoomies);
+                effects.render = true;
+            }
+            //TODO: actions to change the radius of the cursor and rotate it, need
+            //line and circle drawing algorithms to move by one pixel
+            Action::CursorLeft | Action::CursorRight | Action::CursorUp | Action::CursorDown => {
+                let (dx, dy) = match action {
+                    Action::CursorLeft => (-1, 0),
+                    Action::CursorRight => (1, 0),
+                    Action::CursorUp => (0, -1),
+                    _ => (0, 1),
+                };
+                effects.warp_mouse = Some(self.mouse.offset(dx, dy));
+            }
+            Action::ToggleMenu => {
+                self.display_menu_q = !self.display_menu_q;
+            }
+            Action::SaveOrbit => {
+                let c = self.mouse_c();
+                self.saved_orbits.push(c);
+            }
+            Action::ClearOrbits => {
+                self.saved_orbits.clear();
+            }
+            Action::ExportCsv | Action::ExportJson => {
+                effects.task = Some(Task::ExportOrbits {
+                    c: self.mouse_c(),
+                    json: action == Action::ExportJson,
+                });
+            }
+            Action::Screenshot | Action::ScreenshotWithoutHud => {
+                effects.task = Some(Task::Screenshot {
+                    hud: action == Action::Screenshot,
+                });
+            }
+            Action::ExportLocation => effects.task = Some(Task::ExportLocation),
+            Action::ZoomSequence | Action::ZoomSequenceWithOrbit => {
+                //zoom in on the cursor (or the orbit being played back), once
+                //the frames and depth are settled on the command line
+                let c = self.seed();
+                let orbit = if action == Action::ZoomSequenceWithOrbit {
+                    " orbit"
+                } else {
+                    ""
+                };
+                self.command_line = Some(format!(
+                    "zoom {}{:+}i {} {}{}",
+                    c.re, c.im, self.zoom_frames, self.zoom_depth, orbit
+                ));
+                self.message = None;
+                effects.text_input = Some(true);
+            }
+            Action::ExportSvg | Action::ExportSvgWithAxes => {
+                effects.task = Some(Task::ExportSvg {
+                    c: self.seed(),
+                    axes: action == Action::ExportSvgWithAxes,
+                });
+            }
+            Action::About => effects.task = Some(Task::About),
+            Action::CommandLine => {
+                self.command_line = Some(String::new());
+                self.message = None;
+                effects.text_input = Some(true);
+            }
+            Action::ToggleConsole => {
+                self.console.open = !self.console.open;
+                effects.text_input = Some(self.console.open);
+            }
+        }
+        effects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        //the usual -1.5-i to 0.5+i, 0.01 per pixel
+        let view = ComplexBBox {
+            ll: Complex::new(-1.5, -1.0),
+            ur: Complex::new(0.5, 1.0),
+        };
+        App::new(view, 50, (200, 200))
+    }
+
+    fn close(a: Complex<f64>, b: Complex<f64>) -> bool {
+        (a - b).norm() < 1e-12
+    }
+
+    #[test]
+    fn dragging_moves_the_background_then_the_view() {
+        let mut app = app();
+        app.press();
+        app.motion(110, 100, 10, 0, true);
+        assert_eq!(app.motion(130, 90, 20, -10, true), Effects::default());
+        assert_eq!((app.bg_rect_dest.x(), app.bg_rect_dest.y()), (30, -10));
+        assert_eq!(app.release(MouseButton::Other), Effects::default());
+        assert!(close(app.view.ll, Complex::new(-1.5, -1.0)));
+
+        assert_eq!(app.release(MouseButton::Left), Effects::render());
+        assert!(close(app.view.ll, Complex::new(-1.8, -1.1)));
+        assert!(close(app.view.ur, Complex::new(0.2, 0.9)));
+        assert_eq!(app.bg_rect_dest, Rect::new(0, 0, 200, 200));
+        assert_eq!(app.drag, (0, 0));
+    }
+
+    #[test]
+    fn zooming_keeps_the_point_under_the_mouse() {
+        let mut app = app();
+        app.mouse = Point::new(50, 150);
+        let c = app.mouse_c();
+        assert_eq!(app.handle(Action::ZoomIn), Effects::render());
+        assert!(close(app.mouse_c(), c));
+        assert!(close(app.view.ur - app.view.ll, Complex::new(1.0, 1.0)));
+        app.handle(Action::ZoomOut);
+        assert!(close(app.view.ll, Complex::new(-1.5, -1.0)));
+
+        //pinching stretches the middle 150 pixels over the window until the
+        //fingers lift
+        assert_eq!(app.pinch(0.5, 0.5, 0.025, 2), Effects::default());
+        assert_eq!(app.bg_rect_src, Rect::new(25, 25, 150, 150));
+        assert_eq!(app.finger_up(), Effects::render());
+        assert!(close(app.view.ll, Complex::new(-1.25, -0.75)));
+        assert_eq!(app.finger_up(), Effects::default());
+    }
+
+    #[test]
+    fn menu_opens_and_closes() {
+        let mut app = app();
+        app.handle(Action::ToggleMenu);
+        assert!(app.display_menu_q);
+        app.key_up(Some(Action::ToggleMenu));
+        assert!(app.display_menu_q);
+        app.key_up(None);
+        assert!(!app.display_menu_q);
+        app.handle(Action::ToggleMenu);
+        app.release(MouseButton::Other);
+        assert!(!app.display_menu_q);
+    }
+
+    #[test]
+    fn actions_ask_main_for_what_they_need() {
+        let mut app = app();
+        app.mouse = Point::new(100, 100);
+        app.handle(Action::SaveOrbit);
+        assert!(close(app.saved_orbits[0], Complex::new(-0.5, 0.0)));
+        app.handle(Action::ClearOrbits);
+        assert!(app.saved_orbits.is_empty());
+
+        assert_eq!(app.handle(Action::ToggleFullscreen).fullscreen, Some(true));
+        assert_eq!(app.handle(Action::ExitFullscreen).fullscreen, Some(false));
+        assert_eq!(app.handle(Action::ExitFullscreen).fullscreen, None);
+        assert_eq!(
+            app.handle(Action::CursorUp).warp_mouse,
+            Some(Point::new(100, 99))
+        );
+        assert!(app.handle(Action::Quit).quit);
+        assert_eq!(
+            app.handle(Action::ExportJson).task,
+            Some(Task::ExportOrbits {
+                c: app.mouse_c(),
+                json: true
+            })
+        );
+
+        app.handle(Action::MoreIterations);
+        assert_eq!(app.settings.iterations, 100);
+        app.handle(Action::Home);
+        assert_eq!(app.settings.iterations, 50);
+
+        app.handle(Action::ToggleAutoIterations);
+        assert!(app.settings.auto_iterations);
+        app.settings.iterations = 420; //where the automatic limit got to
+        assert!(app.handle(Action::ToggleAutoIterations).render);
+        assert!(!app.settings.auto_iterations);
+        assert_eq!(app.settings.iterations, 420);
+    }
+
+    #[test]
+    fn the_console_takes_typing_while_open() {
+        let mut app = app();
+        app.text_input("x");
+        assert_eq!(app.console.input, "");
+        assert_eq!(app.handle(Action::ToggleConsole).text_input, Some(true));
+        app.text_input("print(1)");
+        assert_eq!(
+            app.typing_key(EditKey::Enter).task,
+            Some(Task::RunScript("print(1)".to_string()))
+        );
+        assert_eq!(app.typing_key(EditKey::Enter), Effects::default());
+        assert_eq!(app.running_scripts, 1);
+        assert_eq!(
+            app.typing_key(EditKey::Escape).task,
+            Some(Task::CancelScripts)
+        );
+        app.running_scripts = 0; //ScriptDone
+        assert_eq!(app.typing_key(EditKey::Escape).text_input, Some(false));
+        assert!(!app.console.open);
+    }
+
+    #[test]
+    fn commands_go_to_exact_coordinates() {
+        let mut app = app();
+        assert_eq!(app.handle(Action::CommandLine).text_input, Some(true));
+        app.text_input("goto -0.75 0.1 1e-6");
+        assert!(app.typing());
+        let effects = app.typing_key(EditKey::Enter);
+        assert_eq!((effects.render, effects.text_input), (true, Some(false)));
+        assert!(!app.typing());
+        assert!(close(app.view.ll, Complex::new(-0.75 - 5e-7, 0.1 - 5e-7)));
+        assert!(close(app.view.ur, Complex::new(-0.75 + 5e-7, 0.1 + 5e-7)));
+
+        app.run_command("orbit -0.75+0.1i");
+        assert_eq!(app.saved_orbits, vec![Complex::new(-0.75, 0.1)]);
+        app.run_command("iter 2000");
+        assert_eq!(app.settings.iterations, 2000);
+        assert!(!app.settings.auto_iterations);
+        app.run_command("radius 4");
+        assert_eq!(app.bailout.radius, 4.0);
+
+        assert_eq!(app.run_command("iter lots"), Effects::default());
+        assert!(app.message.as_ref().unwrap().contains("whole number"));
+        app.handle(Action::CommandLine);
+        assert_eq!(app.message, None);
+        app.typing_key(EditKey::Backspace);
+        assert!(!app.typing());
+    }
+
+    #[test]
+    fn a_smaller_radius_cuts_the_played_orbit_short() {
+        let mut app = app();
+        app.mouse = Point::new(190, 100); //0.4, escapes after a few iterates
+        app.run_command("radius 10");
+        app.handle(Action::TogglePlayback);
+        let long = calc_orbits(app.seed(), &app.bailout).len();
+        for _ in 0..long {
+            app.handle(Action::StepForward);
+        }
+        assert_eq!(app.playback.as_ref().unwrap().index, long - 1);
+
+        app.run_command("radius 2");
+        let short = calc_orbits(app.seed(), &app.bailout).len();
+        assert!(short < long);
+        let p = app.playback.as_mut().unwrap();
+        assert!(p.paused);
+        assert_eq!(p.index, short - 1);
+    }
+
+    #[test]
+    fn screenshots_and_locations_can_be_opened() {
+        let dir = std::env::temp_dir().join(format!("orbits-open-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let view = ComplexBBox {
+            ll: Complex::new(-0.8, 0.05),
+            ur: Complex::new(-0.7, 0.15),
+        };
+        let metadata = ViewMetadata {
+            view,
+            iterations: 300,
+            saved_orbits: vec![Complex::new(-1.0, 0.0)],
+        };
+        let png = dir.join("shot.png");
+        std::fs::write(&png, screenshot::encode(1, 1, &[0; 4], &metadata).unwrap()).unwrap();
+
+        let mut app = app();
+        let command = format!("open {}", png.display());
+        assert!(app.run_command(&command).render);
+        let restored = |app: &App| {
+            (app.view.ll, app.view.ur) == (view.ll, view.ur)
+                && app.settings.iterations == 300
+                && app.saved_orbits == [Complex::new(-1.0, 0.0)]
+        };
+        assert!(restored(&app));
+
+        app.run_command("open no-such-file.png");
+        let message = app.message.clone().unwrap();
+        assert!(
+            message.starts_with("couldn't open no-such-file.png: "),
+            "{}",
+            message
+        );
+        assert!(restored(&app));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn zoom_sequences_are_set_up_on_the_command_line() {
+        let mut app = app();
+        app.mouse = Point::new(100, 100);
+        assert_eq!(
+            app.handle(Action::ZoomSequenceWithOrbit).text_input,
+            Some(true)
+        );
+        assert_eq!(
+            app.command_line.as_deref(),
+            Some("zoom -0.5+0i 120 1000 orbit")
+        );
+        //the numbers typed over the offered ones are offered next time
+        app.command_line = Some("zoom -0.75+0.1i 30 1e6".to_string());
+        let zoom = Task::ZoomSequence {
+            c: Complex::new(-0.75, 0.1),
+            with_orbit: false,
+            frames: 30,
+            depth: 1e6,
+        };
+        assert_eq!(app.typing_key(EditKey::Enter).task, Some(zoom));
+        app.handle(Action::ZoomSequence);
+        assert_eq!(app.command_line.as_deref(), Some("zoom -0.5+0i 30 1000000"));
+    }
+}
@@ -0,0 +1,107 @@
+//Automatic iteration limit.  The starting point comes from how far the view
+//is zoomed in, then the limit keeps doubling while the extra iterations still
+//let a noticeable fraction of the pixels that were unescaped at the limit
+//escape.  Escape times below the old limit don't depend on the limit, so only
+//the unescaped pixels need to be iterated again.
+use crate::complex_bbox::ComplexBBox;
+use crate::kernel::escape_time;
+use rayon::prelude::*;
+use std::convert::TryInto;
+
+pub const MAX_ITERATIONS: u32 = 1 << 16;
+const REFERENCE_WIDTH: f64 = 2.0; //width of the initial view
+const MIN_RESOLVED_FRACTION: f64 = 0.001; //of all pixels in the image
+
+//Number of times the view has been halved in width relative to the initial view
+pub fn zoom_depth(view: &ComplexBBox) -> f64 {
+    (REFERENCE_WIDTH / (view.ur.re - view.ll.re))
+        .log2()
+        .max(0.0)
+}
+
+pub fn starting_iterations(view: &ComplexBBox, base: u32) -> u32 {
+    let iter = (base as f64 * (1.0 + zoom_depth(view))).round();
+    iter.min(MAX_ITERATIONS as f64) as u32
+}
+
+pub fn unescaped_fraction(counts: &[u32], iter: u32) -> f64 {
+    if counts.is_empty() {
+        return 0.0;
+    }
+    let unescaped = counts.par_iter().filter(|n| **n >= iter).count();
+    unescaped as f64 / counts.len() as f64
+}
+
+//Raise the limit on an already rendered `w` x `h` image of `view`, updating
+//`counts` in place.  Returns the new iteration limit.
+pub fn raise(view: &ComplexBBox, w: usize, h: usize, counts: &mut [u32], iter: u32) -> u32 {
+    let (wi, hi): (i32, i32) = (w.try_into().unwrap(), h.try_into().unwrap());
+    let mut iter = iter;
+
+    while iter < MAX_ITERATIONS {
+        let next = iter.saturating_mul(2).min(MAX_ITERATIONS);
+        let unescaped: Vec<usize> = (0..counts.len()).filter(|i| counts[*i] >= iter).collect();
+        if unescaped.is_empty() {
+            break;
+        }
+
+        let extended: Vec<u32> = unescaped
+            .par_iter()
+            .map(|i| {
+                let (x, y) = ((i % w) as i32, (i / w) as i32);
+                escape_time(view.screen_to_complex(x, y, wi, hi), next)
+            })
+            .collect();
+        let resolved = extended.iter().filter(|n| **n < next).count();
+        if (resolved as f64) < MIN_RESOLVED_FRACTION * counts.len() as f64 {
+            break;
+        }
+
+        for (i, n) in unescaped.iter().zip(extended) {
+            counts[*i] = n;
+        }
+        iter = next;
+    }
+    iter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::{self, Kernel};
+    use num::Complex;
+
+    #[test]
+    fn raised_counts_match_a_full_render_at_the_new_limit() {
+        let j = Complex { re: 0.0, im: 1.0 };
+        let view = ComplexBBox {
+            ll: -1.5 - j,
+            ur: 0.5 + j,
+        };
+        let (w, h) = (160, 120);
+        let mut counts = kernel::render_iterations(&view, w, h, 50, Kernel::Scalar);
+        let iter = raise(&view, w, h, &mut counts, 50);
+
+        assert!(iter > 50);
+        assert_eq!(
+            counts,
+            kernel::render_iterations(&view, w, h, iter, Kernel::Scalar)
+        );
+    }
+
+    #[test]
+    fn deeper_views_start_with_more_iterations() {
+        let j = Complex { re: 0.0, im: 1.0 };
+        let home = ComplexBBox {
+            ll: -1.5 - j,
+            ur: 0.5 + j,
+        };
+        let center = Complex::new(-0.75, 0.1);
+        let deep = home.zoom(center, 1.0 / 1024.0);
+
+        assert_eq!(zoom_depth(&home), 0.0);
+        assert!((zoom_depth(&deep) - 10.0).abs() < 1e-9);
+        assert_eq!(starting_iterations(&home, 50), 50);
+        assert_eq!(starting_iterations(&deep, 50), 550);
+    }
+}
@@ -0,0 +1,287 @@
+//Command line arguments, so the viewer can be started straight into a location
+use clap::Parser;
+use num::Complex;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+use orbits::auto_iterations::MAX_ITERATIONS;
+pub use orbits::complex_bbox::bbox;
+use orbits::complex_bbox::ComplexBBox;
+use orbits::zoom_sequence::MAX_FRAMES;
+
+pub const MAX_WINDOW_SIZE: u32 = 16384;
+
+#[derive(Parser, Debug)]
+#[command(name = "orbits", version, about = "Mandelbrot set orbit browser")]
+pub struct Args {
+    /// Center of the view, as RE,IM (e.g. -0.75,0.1)
+    #[arg(long, value_name = "RE,IM", value_parser = parse_complex, allow_hyphen_values = true, conflicts_with = "bbox")]
+    pub center: Option<Complex<f64>>,
+
+    /// Start at the view in a screenshot, or a Kalles Fraktaler, XaoS or Fractint location file
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["center", "zoom", "bbox"])]
+    pub open: Option<PathBuf>,
+
+    /// Magnification, 1 is the initial 2 by 2 view
+    #[arg(long, value_parser = parse_zoom, allow_hyphen_values = true, conflicts_with = "bbox")]
+    pub zoom: Option<f64>,
+
+    /// Corners of the view, as LL_RE,LL_IM,UR_RE,UR_IM
+    #[arg(long, value_name = "LL_RE,LL_IM,UR_RE,UR_IM", value_parser = parse_bbox, allow_hyphen_values = true)]
+    pub bbox: Option<Bbox>,
+
+    /// Window size, as WIDTHxHEIGHT [default: 800x600]
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub size: Option<(u32, u32)>,
+
+    /// Maximum iterations for the background [default: 50]
+    #[arg(long, value_parser = parse_iterations)]
+    pub iterations: Option<u32>,
+
+    /// Frames in the zoom sequences offered by the Z key [default: 120]
+    #[arg(long, value_name = "N", value_parser = parse_frames)]
+    pub zoom_frames: Option<usize>,
+
+    /// How many times the last frame of a zoom sequence is magnified over the first [default: 1000]
+    #[arg(long, value_name = "MAGNIFICATION", value_parser = parse_zoom)]
+    pub zoom_depth: Option<f64>,
+
+    /// Start in fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Saved orbit to start with, as RE,IM (may be repeated)
+    #[arg(long = "orbit", value_name = "RE,IM", value_parser = parse_complex, allow_hyphen_values = true)]
+    pub orbits: Vec<Complex<f64>>,
+
+    /// TrueType font for the coordinates and menu [default: built in DejaVu Sans Mono]
+    #[arg(long, value_name = "PATH")]
+    pub font: Option<PathBuf>,
+
+    /// Config file with colors, keys and defaults [default: ~/.config/orbits/config.toml]
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Log levels, e.g. debug or info,events=trace (targets: events, render, menu, control, script, command) [default: info, or ORBITS_LOG]
+    #[arg(long, value_name = "FILTER")]
+    pub log: Option<String>,
+
+    /// Write the log to a file instead of the terminal, for bug reports
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Record the input to a file, to play it back with --replay
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Play back input recorded with --record, e.g. with SDL_VIDEODRIVER=dummy in tests
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// On exit, write the view, iteration limit and saved orbits as JSON
+    #[arg(long, value_name = "PATH")]
+    pub dump_state: Option<PathBuf>,
+
+    /// Accept JSON-RPC commands on a localhost port, as PORT or 127.0.0.1:PORT
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_listen)]
+    pub listen: Option<SocketAddr>,
+
+    /// Run a Rhai script and exit when it's done, with SDL's dummy video driver unless SDL_VIDEODRIVER is set
+    #[arg(long, value_name = "PATH")]
+    pub script: Option<PathBuf>,
+
+    /// Read commands from standard input, as typed after ":" e.g. goto -0.75 0.1 1e-6
+    #[arg(long)]
+    pub stdin: bool,
+}
+
+//clap wants a Clone + Send + Sync value type, and ComplexBBox is only Copy
+#[derive(Copy, Clone, Debug)]
+pub struct Bbox(pub Complex<f64>, pub Complex<f64>);
+
+impl Args {
+    //The view asked for, if any
+    pub fn initial_view(&self) -> Option<ComplexBBox> {
+        match self.bbox {
+            Some(Bbox(ll, ur)) => Some(ComplexBBox { ll, ur }),
+            None if self.center.is_some() || self.zoom.is_some() => {
+                Some(centered(self.center, self.zoom))
+            }
+            None => None,
+        }
+    }
+}
+
+//2 by 2 view magnified by `zoom`, the usual -1.5-i to 0.5+i by default
+pub fn centered(center: Option<Complex<f64>>, zoom: Option<f64>) -> ComplexBBox {
+    let center = center.unwrap_or(Complex::new(-0.5, 0.0));
+    let half = 1.0 / zoom.unwrap_or(1.0);
+    ComplexBBox {
+        ll: center - Complex::new(half, half),
+        ur: center + Complex::new(half, half),
+    }
+}
+
+fn parse_numbers(s: &str, count: usize, names: &str) -> Result<Vec<f64>, String> {
+    let numbers = s
+        .split(',')
+        .map(|n| {
+            let x = n
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("\"{}\" isn't a number", n.trim()))?;
+            if x.is_finite() {
+                Ok(x)
+            } else {
+                Err(format!("\"{}\" isn't a finite number", n.trim()))
+            }
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    if numbers.len() != count {
+        return Err(format!(
+            "expected {} comma separated numbers ({}), got {}",
+            count,
+            names,
+            numbers.len()
+        ));
+    }
+    Ok(numbers)
+}
+
+fn parse_complex(s: &str) -> Result<Complex<f64>, String> {
+    let v = parse_numbers(s, 2, "RE,IM")?;
+    Ok(Complex::new(v[0], v[1]))
+}
+
+fn parse_bbox(s: &str) -> Result<Bbox, String> {
+    let v = parse_numbers(s, 4, "LL_RE,LL_IM,UR_RE,UR_IM")?;
+    let view = bbox(Complex::new(v[0], v[1]), Complex::new(v[2], v[3]))?;
+    Ok(Bbox(view.ll, view.ur))
+}
+
+fn parse_zoom(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(zoom) if zoom > 0.0 && zoom.is_finite() => Ok(zoom),
+        _ => Err(format!("\"{}\" isn't a positive number", s)),
+    }
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let error = || {
+        format!(
+            "\"{}\" isn't WIDTHxHEIGHT with sides from 1 to {}",
+            s, MAX_WINDOW_SIZE
+        )
+    };
+    let (w, h) = s.split_once(['x', 'X']).ok_or_else(error)?;
+    let side = |n: &str| match n.trim().parse::<u32>() {
+        Ok(n) if (1..=MAX_WINDOW_SIZE).contains(&n) => Ok(n),
+        _ => Err(error()),
+    };
+    Ok((side(w)?, side(h)?))
+}
+
+//Anyone who can connect can write files, so only local connections
+fn parse_listen(s: &str) -> Result<SocketAddr, String> {
+    let addr = match s.parse::<u16>() {
+        Ok(port) => SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        Err(_) => s
+            .parse::<SocketAddr>()
+            .map_err(|_| format!("\"{}\" isn't PORT or ADDRESS:PORT", s))?,
+    };
+    if !addr.ip().is_loopback() {
+        return Err(format!("{} isn't a loopback address", addr.ip()));
+    }
+    Ok(addr)
+}
+
+fn parse_iterations(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(n) if (1..=MAX_ITERATIONS).contains(&n) => Ok(n),
+        _ => Err(format!(
+            "\"{}\" isn't a whole number from 1 to {}",
+            s, MAX_ITERATIONS
+        )),
+    }
+}
+
+fn parse_frames(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(n) if (1..=MAX_FRAMES).contains(&n) => Ok(n),
+        _ => Err(format!(
+            "\"{}\" isn't a whole number from 1 to {}",
+            s, MAX_FRAMES
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::try_parse_from(std::iter::once("orbits").chain(args.iter().copied()))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn defaults_match_the_initial_view() {
+        let args = parse(&[]).unwrap();
+        assert!(args.initial_view().is_none());
+        assert_eq!(
+            (args.size, args.iterations, args.fullscreen),
+            (None, None, false)
+        );
+        let view = centered(None, None);
+        assert_eq!(
+            (view.ll, view.ur),
+            (Complex::new(-1.5, -1.0), Complex::new(0.5, 1.0))
+        );
+    }
+
+    #[test]
+    fn locations_from_center_and_zoom_or_bbox() {
+        let args = parse(&["--center", "-0.75,0.1", "--zoom", "4", "--orbit", "-1,0"]).unwrap();
+        let view = args.initial_view().unwrap();
+        assert_eq!(
+            (view.ll, view.ur),
+            (Complex::new(-1.0, -0.15), Complex::new(-0.5, 0.35))
+        );
+        assert_eq!(args.orbits, vec![Complex::new(-1.0, 0.0)]);
+
+        let args = parse(&["--bbox", "-2,-1.5,1,1.5", "--size", "1024x768"]).unwrap();
+        let view = args.initial_view().unwrap();
+        assert_eq!(
+            (view.ll, view.ur),
+            (Complex::new(-2.0, -1.5), Complex::new(1.0, 1.5))
+        );
+        assert_eq!(args.size, Some((1024, 768)));
+
+        let args = parse(&["--listen", "7000"]).unwrap();
+        assert_eq!(args.listen, Some("127.0.0.1:7000".parse().unwrap()));
+    }
+
+    #[test]
+    fn invalid_input_is_explained() {
+        let error = parse(&["--zoom", "-3"]).unwrap_err();
+        assert!(error.contains("isn't a positive number"), "{}", error);
+        let error = parse(&["--bbox", "1,1,0,0"]).unwrap_err();
+        assert!(error.contains("must be below and left"), "{}", error);
+        let error = parse(&["--center", "1"]).unwrap_err();
+        assert!(
+            error.contains("expected 2 comma separated numbers"),
+            "{}",
+            error
+        );
+        let error = parse(&["--size", "800by600"]).unwrap_err();
+        assert!(error.contains("WIDTHxHEIGHT"), "{}", error);
+        let error = parse(&["--iterations", "0"]).unwrap_err();
+        assert!(error.contains("from 1 to"), "{}", error);
+        let error = parse(&["--zoom-frames", "ten"]).unwrap_err();
+        assert!(error.contains("from 1 to"), "{}", error);
+        let error = parse(&["--listen", "0.0.0.0:7000"]).unwrap_err();
+        assert!(error.contains("isn't a loopback address"), "{}", error);
+        let error = parse(&["--center", "0,0", "--bbox", "-1,-1,1,1"]).unwrap_err();
+        assert!(error.contains("cannot be used with"), "{}", error);
+    }
+}
@@ -0,0 +1,257 @@
+//Commands typed after `:` in the window or on stdin with --stdin, for getting
+//to an exact c instead of the nearest pixel:
+//
+//    goto RE IM [WIDTH]   center the view on RE+IMi, WIDTH across if given
+//    orbit RE+IMi         save the orbit of c, or as RE IM
+//    iter N               iteration limit for the background
+//    radius R             escape radius for the orbits drawn and exported
+//    open PATH            go back to the view in a screenshot or location file
+//    zoom RE+IMi [FRAMES [DEPTH]] [orbit]
+//                         write a zoom sequence into RE+IMi, DEPTH times
+//                         magnified by the end, with its orbit if asked
+use num::Complex;
+use std::str::SplitWhitespace;
+
+use crate::auto_iterations::MAX_ITERATIONS;
+use crate::zoom_sequence::MAX_FRAMES;
+
+const USAGE: [(&str, &str); 6] = [
+    ("goto", "goto RE IM [WIDTH]"),
+    ("orbit", "orbit RE+IMi"),
+    ("iter", "iter N"),
+    ("radius", "radius R"),
+    ("open", "open PATH"),
+    ("zoom", "zoom RE+IMi [FRAMES [DEPTH]] [orbit]"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Entry {
+    Goto {
+        center: Complex<f64>,
+        width: Option<f64>, //of the view, or keep the current one
+    },
+    Orbit(Complex<f64>),
+    Iterations(u32),
+    Radius(f64),
+    Open(String),
+    Zoom {
+        c: Complex<f64>,
+        frames: Option<usize>, //or the last ones used
+        depth: Option<f64>,
+        with_orbit: bool,
+    },
+}
+
+fn number(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(x),
+        _ => Err(format!("\"{}\" isn't a number", text)),
+    }
+}
+
+//"-0.75+0.1i", "0.5-i", "2.5e-3i"...
+fn imaginary(text: &str) -> Result<Complex<f64>, String> {
+    let body = &text[..text.len() - 1];
+    //the sign between the parts, not one in an exponent
+    let split = body
+        .char_indices()
+        .rev()
+        .find(|&(i, c)| (c == '+' || c == '-') && i > 0 && !body[..i].ends_with(['e', 'E']))
+        .map(|(i, _)| i);
+    let (re, im) = match split {
+        Some(i) => (number(&body[..i])?, &body[i..]),
+        None => (0.0, body),
+    };
+    let im = match im {
+        "" | "+" => 1.0,
+        "-" => -1.0,
+        _ => number(im).map_err(|_| format!("\"{}\" isn't a complex number", text))?,
+    };
+    Ok(Complex::new(re, im))
+}
+
+//As RE+IMi, or RE then IM, which can be left out when it's 0
+fn complex(args: &mut SplitWhitespace) -> Option<Result<Complex<f64>, String>> {
+    let first = args.next()?;
+    if first.ends_with('i') {
+        return Some(imaginary(first));
+    }
+    let re = match number(first) {
+        Ok(re) => re,
+        Err(e) => return Some(Err(e)),
+    };
+    Some(match args.next() {
+        Some(im) => number(im).map(|im| Complex::new(re, im)),
+        None => Ok(Complex::new(re, 0.0)),
+    })
+}
+
+pub fn parse(line: &str) -> Result<Entry, String> {
+    let mut args = line.split_whitespace();
+    let name = args
+        .next()
+        .ok_or("type a command: goto, orbit, iter, radius, open or zoom")?;
+    let usage = match USAGE.iter().find(|(n, _)| *n == name) {
+        Some((_, usage)) => format!("usage: {}", usage),
+        None => {
+            return Err(format!(
+                "unknown command \"{}\", expected goto, orbit, iter, radius, open or zoom",
+                name
+            ))
+        }
+    };
+    let entry = match name {
+        "goto" => {
+            let center = complex(&mut args).ok_or(&usage)??;
+            let width = args.next().map(number).transpose()?;
+            if matches!(width, Some(w) if w <= 0.0) {
+                return Err("the width must be more than 0".to_string());
+            }
+            Entry::Goto { center, width }
+        }
+        "orbit" => Entry::Orbit(complex(&mut args).ok_or(&usage)??),
+        "iter" => match args.next().map(str::parse::<u32>) {
+            Some(Ok(n)) if (1..=MAX_ITERATIONS).contains(&n) => Entry::Iterations(n),
+            Some(_) => {
+                return Err(format!(
+                    "the iteration limit must be a whole number from 1 to {}",
+                    MAX_ITERATIONS
+                ))
+            }
+            None => return Err(usage),
+        },
+        //the rest of the line, paths can have spaces
+        "open" => match line.trim_start()[name.len()..].trim() {
+            "" => return Err(usage),
+            path => return Ok(Entry::Open(path.to_string())),
+        },
+        "radius" => match args.next().map(number).transpose()? {
+            //orbits can come back from anywhere inside 2
+            Some(r) if r >= 2.0 => Entry::Radius(r),
+            Some(_) => return Err("the escape radius must be at least 2".to_string()),
+            None => return Err(usage),
+        },
+        _ => {
+            let c = complex(&mut args).ok_or(&usage)??;
+            let mut rest: Vec<&str> = args.by_ref().collect();
+            let with_orbit = rest.last() == Some(&"orbit");
+            if with_orbit {
+                rest.pop();
+            }
+            let frames = match rest.first().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if (1..=MAX_FRAMES).contains(&n) => Some(n),
+                Some(_) => {
+                    return Err(format!(
+                        "the number of frames must be a whole number from 1 to {}",
+                        MAX_FRAMES
+                    ))
+                }
+                None => None,
+            };
+            let depth = rest.get(1).map(|d| number(d)).transpose()?;
+            if matches!(depth, Some(d) if d <= 0.0) {
+                return Err("the depth must be more than 0".to_string());
+            }
+            if let Some(extra) = rest.get(2) {
+                return Err(format!("unexpected \"{}\", {}", extra, usage));
+            }
+            Entry::Zoom {
+                c,
+                frames,
+                depth,
+                with_orbit,
+            }
+        }
+    };
+    match args.next() {
+        Some(extra) => Err(format!("unexpected \"{}\", {}", extra, usage)),
+        None => Ok(entry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_take_exact_coordinates() {
+        assert_eq!(
+            parse("goto -0.743643887 0.131825904 1e-6"),
+            Ok(Entry::Goto {
+                center: Complex::new(-0.743643887, 0.131825904),
+                width: Some(1e-6)
+            })
+        );
+        assert_eq!(
+            parse("  goto -1.5e-2-2.5E-3i "),
+            Ok(Entry::Goto {
+                center: Complex::new(-0.015, -0.0025),
+                width: None
+            })
+        );
+        assert_eq!(
+            parse("orbit -0.75+0.1i"),
+            Ok(Entry::Orbit(Complex::new(-0.75, 0.1)))
+        );
+        assert_eq!(parse("orbit -i"), Ok(Entry::Orbit(Complex::new(0.0, -1.0))));
+        assert_eq!(
+            parse("orbit 0.25"),
+            Ok(Entry::Orbit(Complex::new(0.25, 0.0)))
+        );
+        assert_eq!(parse("iter 2000"), Ok(Entry::Iterations(2000)));
+        assert_eq!(parse("radius 4"), Ok(Entry::Radius(4.0)));
+        assert_eq!(
+            parse("open  My Shots/deep.png "),
+            Ok(Entry::Open("My Shots/deep.png".to_string()))
+        );
+        assert_eq!(
+            parse("zoom -0.75+0.1i 60 1e6 orbit"),
+            Ok(Entry::Zoom {
+                c: Complex::new(-0.75, 0.1),
+                frames: Some(60),
+                depth: Some(1e6),
+                with_orbit: true
+            })
+        );
+        assert_eq!(
+            parse("zoom -0.75+0.1i"),
+            Ok(Entry::Zoom {
+                c: Complex::new(-0.75, 0.1),
+                frames: None,
+                depth: None,
+                with_orbit: false
+            })
+        );
+    }
+
+    #[test]
+    fn mistakes_say_what_was_expected() {
+        assert_eq!(parse("goto"), Err("usage: goto RE IM [WIDTH]".to_string()));
+        assert_eq!(
+            parse("orbit 1+xi"),
+            Err("\"1+xi\" isn't a complex number".to_string())
+        );
+        assert_eq!(
+            parse("orbit 1 2 3"),
+            Err("unexpected \"3\", usage: orbit RE+IMi".to_string())
+        );
+        assert_eq!(
+            parse("goto 0 0 -1"),
+            Err("the width must be more than 0".to_string())
+        );
+        assert!(parse("iter 0").unwrap_err().contains("from 1 to"));
+        assert_eq!(
+            parse("radius 1"),
+            Err("the escape radius must be at least 2".to_string())
+        );
+        assert!(parse("zoom 0+0i 0").unwrap_err().contains("from 1 to"));
+        assert_eq!(
+            parse("zoom 0+0i 10 -2"),
+            Err("the depth must be more than 0".to_string())
+        );
+        assert!(parse("teleport 2")
+            .unwrap_err()
+            .starts_with("unknown command \"teleport\""));
+        assert!(parse("").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use num::Complex;
+
+use crate::geometry::Point;
+
+//how far off screen, from the center, complex_to_screen goes
+pub const SCREEN_LIMIT: f64 = (1 << 24) as f64;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ComplexBBox {
+    pub ll: Complex<f64>,
+    pub ur: Complex<f64>,
+}
+
+impl ComplexBBox {
+    pub fn screen_to_complex(&self, x: i32, y: i32, w: i32, h: i32) -> Complex<f64> {
+        let (x, y, w, h) = (x as f64, y as f64, w as f64, h as f64);
+        let (lower, left) = (self.ll.im, self.ll.re);
+        let (upper, right) = (self.ur.im, self.ur.re);
+
+        Complex {
+            re: (left + (x / w) * (right - left)),
+            im: (upper + (y / h) * (lower - upper)),
+        }
+    }
+
+    //Pixel containing `c`, rounding down, so everything in the pixel whose top
+    //left corner screen_to_complex gives maps back to it.  Points more than
+    //SCREEN_LIMIT pixels off screen (orbits of deep zooms) are pulled in toward
+    //the center along the same direction: lines to them keep their slope on
+    //screen, and stay far inside what SDL can draw.
+    pub fn complex_to_screen(&self, c: Complex<f64>, w: i32, h: i32) -> Point {
+        let Complex { re, im } = c;
+        let (w, h) = (w as f64, h as f64);
+        let (lower, left) = (self.ll.im, self.ll.re);
+        let (upper, right) = (self.ur.im, self.ur.re);
+        let x = (re - left) * w / (right - left);
+        let y = (im - upper) * h / (lower - upper);
+
+        //infinite (or overflowing) components only keep their sign, otherwise
+        //the scaling below makes NaN of them, which `as` turns into 0
+        let finite = |d: f64| {
+            if d.is_finite() {
+                d
+            } else {
+                d.signum() * f64::MAX
+            }
+        };
+        let (dx, dy) = (finite(x - w / 2.0), finite(y - h / 2.0));
+        let distance = dx.abs().max(dy.abs());
+        let (x, y) = if distance > SCREEN_LIMIT {
+            let scale = SCREEN_LIMIT / distance;
+            (w / 2.0 + dx * scale, h / 2.0 + dy * scale)
+        } else {
+            (x, y)
+        };
+        Point::new(x.floor() as i32, y.floor() as i32)
+    }
+
+    pub fn complex_deltas(&self, w: i32, h: i32, dx: i32, dy: i32) -> Complex<f64> {
+        let (w, h) = (w as f64, h as f64);
+        let (dx, dy) = (dx as f64, dy as f64);
+        let left = self.ll.re;
+        let right = self.ur.re;
+        let lower = self.ll.im;
+        let upper = self.ur.im;
+
+        Complex {
+            re: ((dx / w) * (right - left)),
+            im: ((-dy / h) * (upper - lower)),
+        }
+    }
+
+    pub fn zoom(&self, position: Complex<f64>, scale_factor: f64) -> ComplexBBox {
+        let Complex { re: x, im: y } = position;
+        let new_lower = y - (y - self.ll.im) * scale_factor;
+        let new_upper = new_lower + (self.ur.im - self.ll.im) * scale_factor;
+        let new_left = x - (x - self.ll.re) * scale_factor;
+        let new_right = new_left + (self.ur.re - self.ll.re) * scale_factor;
+
+        ComplexBBox {
+            ll: Complex {
+                re: new_left,
+                im: new_lower,
+            },
+            ur: Complex {
+                re: new_right,
+                im: new_upper,
+            },
+        }
+    }
+}
+
+//The view with these corners, checked the same way wherever one comes from:
+//the command line, config, control socket, scripts and files
+pub fn bbox(ll: Complex<f64>, ur: Complex<f64>) -> Result<ComplexBBox, String> {
+    if ![ll.re, ll.im, ur.re, ur.im].iter().all(|x| x.is_finite()) {
+        return Err(format!(
+            "the corners ({},{}) and ({},{}) must be finite",
+            ll.re, ll.im, ur.re, ur.im
+        ));
+    }
+    if ll.re >= ur.re || ll.im >= ur.im {
+        return Err(format!(
+            "the lower left corner ({},{}) must be below and left of the upper right ({},{})",
+            ll.re, ll.im, ur.re, ur.im
+        ));
+    }
+    Ok(ComplexBBox { ll, ur })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const W: i32 = 800;
+    const H: i32 = 600;
+
+    fn home() -> ComplexBBox {
+        ComplexBBox {
+            ll: Complex::new(-1.5, -1.0),
+            ur: Complex::new(0.5, 1.0),
+        }
+    }
+
+    //views from the whole set down to deep zooms, with any aspect ratio
+    fn views() -> impl Strategy<Value = ComplexBBox> {
+        (-2.0..1.0, -1.5..1.5, -12.0..0.5, 0.25..4.0).prop_map(|(re, im, size, aspect)| {
+            let width: f64 = 10.0_f64.powf(size);
+            ComplexBBox {
+                ll: Complex::new(re, im),
+                ur: Complex::new(re + width, im + width / aspect),
+            }
+        })
+    }
+
+    //equal up to rounding, relative to the view and to the size of the numbers
+    fn close(a: Complex<f64>, b: Complex<f64>, view: &ComplexBBox) -> bool {
+        let size = (view.ur - view.ll).norm();
+        (a - b).norm() <= size * 1e-9 + a.norm().max(b.norm()) * 1e-14
+    }
+
+    #[test]
+    fn pixels_round_down() {
+        let view = home();
+        let pixel = 2.0 / W as f64; //width of a pixel
+        let at = |re, im| view.complex_to_screen(Complex::new(re, im), W, H);
+        assert_eq!(at(-1.5, 1.0), Point::new(0, 0));
+        assert_eq!(at(0.5, -1.0), Point::new(W, H));
+        //truncation would put these on the first row and column
+        assert_eq!(
+            at(-1.5 - pixel / 2.0, 1.0 + pixel / 2.0),
+            Point::new(-1, -1)
+        );
+        assert_eq!(at(-1.5 + pixel * 0.99, 1.0), Point::new(0, 0));
+    }
+
+    #[test]
+    fn far_off_points_are_clamped_along_their_direction() {
+        let view = home().zoom(Complex::new(-0.75, 0.1), 1e-9);
+        let p = view.complex_to_screen(Complex::new(2.0, 1.0), W, H);
+        let (dx, dy) = (p.x() as f64 - W as f64 / 2.0, p.y() as f64 - H as f64 / 2.0);
+        assert_eq!(dx, SCREEN_LIMIT);
+        //2.75 right and 0.9 up from the center, pixels are 1/400 wide and 1/300 tall
+        let slope = -(0.9 * 300.0) / (2.75 * 400.0);
+        assert!((dy / dx - slope).abs() < 1e-6, "{:?}", p);
+
+        let limit = SCREEN_LIMIT as i32;
+        let at = |re, im| view.complex_to_screen(Complex::new(re, im), W, H);
+        assert_eq!(at(f64::INFINITY, 0.0), Point::new(W / 2 + limit, H / 2));
+        assert_eq!(at(f64::NEG_INFINITY, 0.0), Point::new(W / 2 - limit, H / 2));
+        assert_eq!(at(-0.75, f64::INFINITY), Point::new(W / 2, H / 2 - limit));
+    }
+
+    proptest! {
+        #[test]
+        fn screen_to_complex_and_back_stays_within_a_pixel(
+            view in views(),
+            x in 0..W,
+            y in 0..H,
+        ) {
+            let p = view.complex_to_screen(view.screen_to_complex(x, y, W, H), W, H);
+            prop_assert!((p.x() - x).abs() <= 1 && (p.y() - y).abs() <= 1, "{:?}", p);
+        }
+
+        #[test]
+        fn screen_points_are_clamped(view in views(), re in -1e6..1e6, im in -1e6..1e6) {
+            let p = view.complex_to_screen(Complex::new(re, im), W, H);
+            let limit = SCREEN_LIMIT + W.max(H) as f64;
+            prop_assert!((p.x() as f64).abs() <= limit && (p.y() as f64).abs() <= limit);
+        }
+
+        #[test]
+        fn zoom_keeps_its_center_fixed(view in views(), x in 0..W, y in 0..H, scale in 0.01..100.0) {
+            let c = view.screen_to_complex(x, y, W, H);
+            let zoomed = view.zoom(c, scale);
+            prop_assert!(close(zoomed.screen_to_complex(x, y, W, H), c, &zoomed));
+        }
+
+        #[test]
+        fn zoom_in_then_out_restores_the_view(view in views(), x in 0..W, y in 0..H, scale in 0.01..100.0) {
+            let c = view.screen_to_complex(x, y, W, H);
+            let back = view.zoom(c, scale).zoom(c, 1.0 / scale);
+            prop_assert!(close(back.ll, view.ll, &view) && close(back.ur, view.ur, &view));
+        }
+
+        #[test]
+        fn pan_deltas_add_up(
+            view in views(),
+            (dx1, dy1) in (-2000..2000, -2000..2000),
+            (dx2, dy2) in (-2000..2000, -2000..2000),
+        ) {
+            let sum = view.complex_deltas(W, H, dx1, dy1) + view.complex_deltas(W, H, dx2, dy2);
+            prop_assert!(close(sum, view.complex_deltas(W, H, dx1 + dx2, dy1 + dy2), &view));
+            //dragging right and down moves the view left and up
+            let d = view.complex_deltas(W, H, 1, 1);
+            prop_assert!(d.re > 0.0 && d.im < 0.0);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//User settings from a TOML file, by default $XDG_CONFIG_HOME/orbits/config.toml
+//(~/.config/orbits/config.toml), for example:
+//
+//    font_size = 14
+//    iterations = 200
+//    window_size = [1024, 768]
+//
+//    [view]
+//    center = [-0.75, 0.1]
+//    zoom = 4.0
+//
+//    [colors]
+//    orbit = "#00ff00"
+//    menu_background = "#f5f5f5e6"
+//
+//    [keys]
+//    quit = "F10"
+//    save_orbit = ["Space", "MouseMiddle"]
+//
+//Everything is optional, command line arguments win over the file.
+use log::info;
+use num::Complex;
+use sdl2::pixels::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli;
+use crate::keymap::Keymap;
+use orbits::auto_iterations::MAX_ITERATIONS;
+use orbits::complex_bbox::ComplexBBox;
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    font_size: Option<u16>,
+    iterations: Option<u32>,
+    window_size: Option<[u32; 2]>,
+    view: Option<ViewFile>,
+    colors: BTreeMap<String, String>,
+    keys: BTreeMap<String, Inputs>,
+}
+
+//one key or a list of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Inputs {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ViewFile {
+    center: Option<[f64; 2]>,
+    zoom: Option<f64>,
+    bbox: Option<[f64; 4]>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Colors {
+    pub orbit_first: Color, //first segment of the orbit under the cursor
+    pub orbit: Color,
+    pub saved_first: Color,
+    pub saved: Color,
+    pub background: Color,
+    pub coordinates_text: Color,
+    pub coordinates_background: Color,
+    pub menu_background: Color,
+    pub menu_text: Color,
+    pub menu_highlight_text: Color,
+    pub menu_highlight_background: Color,
+    pub menu_hint_text: Color,
+}
+
+impl Default for Colors {
+    fn default() -> Colors {
+        Colors {
+            orbit_first: Color::RGBA(255, 0, 0, 255),
+            orbit: Color::RGBA(0, 255, 0, 255),
+            saved_first: Color::RGBA(255, 0, 255, 255),
+            saved: Color::RGBA(0, 255, 255, 255),
+            background: Color::RGBA(255, 255, 255, 255),
+            coordinates_text: Color::RGBA(125, 0, 125, 255),
+            coordinates_background: Color::RGBA(200, 200, 200, 255),
+            menu_background: Color::RGBA(245, 245, 245, 230),
+            menu_text: Color::RGBA(240, 170, 0, 255),
+            menu_highlight_text: Color::RGBA(240, 170, 0, 255),
+            menu_highlight_background: Color::RGBA(100, 0, 100, 255),
+            menu_hint_text: Color::RGBA(120, 120, 120, 255),
+        }
+    }
+}
+
+impl Colors {
+    fn by_name(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "orbit_first" => Some(&mut self.orbit_first),
+            "orbit" => Some(&mut self.orbit),
+            "saved_first" => Some(&mut self.saved_first),
+            "saved" => Some(&mut self.saved),
+            "background" => Some(&mut self.background),
+            "coordinates_text" => Some(&mut self.coordinates_text),
+            "coordinates_background" => Some(&mut self.coordinates_background),
+            "menu_background" => Some(&mut self.menu_background),
+            "menu_text" => Some(&mut self.menu_text),
+            "menu_highlight_text" => Some(&mut self.menu_highlight_text),
+            "menu_highlight_background" => Some(&mut self.menu_highlight_background),
+            "menu_hint_text" => Some(&mut self.menu_hint_text),
+            _ => None,
+        }
+    }
+}
+
+//"#rrggbb" or "#rrggbbaa"
+fn parse_color(s: &str) -> Result<Color, String> {
+    let hex = s.strip_prefix('#').unwrap_or("");
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    let color = match hex.len() {
+        6 if hex.is_ascii() => byte(0)
+            .and_then(|r| Ok(Color::RGBA(r, byte(2)?, byte(4)?, 255)))
+            .ok(),
+        8 if hex.is_ascii() => byte(0)
+            .and_then(|r| Ok(Color::RGBA(r, byte(2)?, byte(4)?, byte(6)?)))
+            .ok(),
+        _ => None,
+    };
+    color.ok_or(format!("\"{}\" isn't a #rrggbb or #rrggbbaa color", s))
+}
+
+pub struct Config {
+    pub font_size: u16,
+    pub iterations: Option<u32>,
+    pub window_size: Option<(u32, u32)>,
+    pub view: Option<ComplexBBox>,
+    pub colors: Colors,
+    pub keys: Keymap,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            font_size: 12,
+            iterations: None,
+            window_size: None,
+            view: None,
+            colors: Colors::default(),
+            keys: Keymap::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let file: ConfigFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut config = Config::default();
+
+        if let Some(size) = file.font_size {
+            if !(6..=72).contains(&size) {
+                return Err(format!("font_size: {} isn't from 6 to 72", size));
+            }
+            config.font_size = size;
+        }
+        if let Some(iterations) = file.iterations {
+            if !(1..=MAX_ITERATIONS).contains(&iterations) {
+                return Err(format!(
+                    "iterations: {} isn't from 1 to {}",
+                    iterations, MAX_ITERATIONS
+                ));
+            }
+            config.iterations = Some(iterations);
+        }
+        if let Some([w, h]) = file.window_size {
+            if !(1..=cli::MAX_WINDOW_SIZE).contains(&w) || !(1..=cli::MAX_WINDOW_SIZE).contains(&h)
+            {
+                return Err(format!(
+                    "window_size: sides must be from 1 to {}",
+                    cli::MAX_WINDOW_SIZE
+                ));
+            }
+            config.window_size = Some((w, h));
+        }
+        if let Some(view) = file.view {
+            config.view = Some(view.to_bbox().map_err(|e| format!("view: {}", e))?);
+        }
+
+        for (name, value) in &file.colors {
+            let color = parse_color(value).map_err(|e| format!("colors.{}: {}", name, e))?;
+            match config.colors.by_name(name) {
+                Some(slot) => *slot = color,
+                None => return Err(format!("colors: unknown color \"{}\"", name)),
+            }
+        }
+
+        for (name, inputs) in file.keys {
+            let inputs = match inputs {
+                Inputs::One(input) => vec![input],
+                Inputs::Many(inputs) => inputs,
+            };
+            config
+                .keys
+                .bind(&name, &inputs)
+                .map_err(|e| format!("keys.{}: {}", name, e))?;
+        }
+        config.keys.validate().map_err(|e| format!("keys: {}", e))?;
+
+        Ok(config)
+    }
+
+    //The file given on the command line, which has to exist, or the one in
+    //the user's config directory if there is one
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Couldn't read config file {}: {}", path.display(), e))?;
+        info!("Using config file {}", path.display());
+        Config::parse(&text).map_err(|e| format!("Config file {}: {}", path.display(), e))
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("orbits").join("config.toml"))
+}
+
+impl ViewFile {
+    fn to_bbox(&self) -> Result<ComplexBBox, String> {
+        if let Some([ll_re, ll_im, ur_re, ur_im]) = self.bbox {
+            if self.center.is_some() || self.zoom.is_some() {
+                return Err("use either bbox or center and zoom, not both".to_string());
+            }
+            return cli::bbox(Complex::new(ll_re, ll_im), Complex::new(ur_re, ur_im));
+        }
+        let center = self.center.map(|[re, im]| Complex::new(re, im));
+        if let Some(zoom) = self.zoom {
+            if !(zoom > 0.0 && zoom.is_finite()) {
+                return Err(format!("zoom {} isn't a positive number", zoom));
+            }
+        }
+        Ok(cli::centered(center, self.zoom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{Action, Input};
+    use sdl2::keyboard::Keycode;
+    use sdl2::mouse::MouseButton;
+
+    #[test]
+    fn empty_file_is_the_built_in_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.font_size, 12);
+        assert_eq!(config.colors, Colors::default());
+        assert_eq!(
+            config.keys.inputs(Action::Quit),
+            vec![Input::Key(Keycode::Q, false)]
+        );
+        assert!(config.view.is_none() && config.iterations.is_none());
+    }
+
+    #[test]
+    fn settings_override_the_defaults() {
+        let config = Config::parse(
+            r##"
+font_size = 14
+iterations = 200
+window_size = [1024, 768]
+
+[view]
+center = [-0.75, 0.1]
+zoom = 4.0
+
+[colors]
+orbit = "#0000ff"
+menu_background = "#10203040"
+
+[keys]
+quit = "F10"
+save_orbit = ["Space", "MouseMiddle"]
+"##,
+        )
+        .unwrap();
+        assert_eq!(config.font_size, 14);
+        assert_eq!(config.iterations, Some(200));
+        assert_eq!(config.window_size, Some((1024, 768)));
+        let view = config.view.unwrap();
+        assert_eq!(
+            (view.ll, view.ur),
+            (Complex::new(-1.0, -0.15), Complex::new(-0.5, 0.35))
+        );
+        assert_eq!(config.colors.orbit, Color::RGBA(0, 0, 255, 255));
+        assert_eq!(config.colors.menu_background, Color::RGBA(16, 32, 48, 64));
+        assert_eq!(
+            config.keys.action(Input::Key(Keycode::F10, false)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            config.keys.inputs(Action::SaveOrbit),
+            vec![
+                Input::Key(Keycode::Space, false),
+                Input::Mouse(MouseButton::Middle)
+            ]
+        );
+    }
+
+    #[test]
+    fn mistakes_are_pointed_out() {
+        let error = |text: &str| Config::parse(text).err().unwrap();
+        assert!(error("font_sise = 12").contains("unknown field `font_sise`"));
+        assert_eq!(
+            error("[colors]\norbit = \"green\""),
+            "colors.orbit: \"green\" isn't a #rrggbb or #rrggbbaa color"
+        );
+        assert_eq!(
+            error("[colors]\norbits = \"#00ff00\""),
+            "colors: unknown color \"orbits\""
+        );
+        assert_eq!(
+            error("[keys]\nmenu = \"Q\""),
+            "keys: \"quit\" and \"menu\" are both bound to Q"
+        );
+        assert!(error("[view]\nzoom = 0").contains("isn't a positive number"));
+        assert!(error("[view]\nbbox = [1, 1, 0, 0]").contains("must be below and left"));
+        assert!(error("iterations = 0").starts_with("iterations:"));
+    }
+}
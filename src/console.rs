@@ -0,0 +1,79 @@
+//The scripting console: a line being typed and the output above it.  main
+//draws it over the view and hands the submitted lines to the script thread.
+use std::collections::VecDeque;
+
+const MAX_LINES: usize = 200; //of output kept for scrolling back
+
+#[derive(Debug, Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    lines: VecDeque<String>, //oldest first
+}
+
+impl Console {
+    //Add output, a line at a time
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            if self.lines.len() == MAX_LINES {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(line.to_string());
+        }
+    }
+
+    //Up to the last `n` lines of output, oldest first
+    pub fn last_lines(&self, n: usize) -> impl Iterator<Item = &String> {
+        self.lines.iter().skip(self.lines.len().saturating_sub(n))
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.input.push_str(text);
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    //The line typed so far, echoed to the output, unless it's blank
+    pub fn submit(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.input);
+        if line.trim().is_empty() {
+            return None;
+        }
+        self.print(&format!("> {}", line));
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_lines_are_echoed_and_output_is_capped() {
+        let mut console = Console::default();
+        console.type_text("print(1");
+        console.type_text("+x");
+        console.backspace();
+        console.backspace();
+        console.type_text(")");
+        assert_eq!(console.submit(), Some("print(1)".to_string()));
+        assert_eq!(console.input, "");
+        console.type_text("  ");
+        assert_eq!(console.submit(), None);
+        console.print("1\n2");
+        assert_eq!(
+            console.last_lines(3).collect::<Vec<_>>(),
+            ["> print(1)", "1", "2"]
+        );
+        for i in 0..MAX_LINES {
+            console.print(&i.to_string());
+        }
+        assert_eq!(console.last_lines(usize::MAX).count(), MAX_LINES);
+        assert_eq!(
+            console.last_lines(1).next().unwrap(),
+            &(MAX_LINES - 1).to_string()
+        );
+    }
+}
@@ -0,0 +1,511 @@
+//Control of the running viewer from scripts and notebooks: JSON-RPC 2.0 over
+//TCP on the loopback interface, one request or response per line, e.g.
+//
+//    {"jsonrpc":"2.0","id":1,"method":"set_view","params":{"center":[-0.75,0.1],"zoom":20}}
+//    {"jsonrpc":"2.0","id":1,"result":{"view":{"ll":[...],"ur":[...]},...}}
+//
+//Methods and their params:
+//    set_view        {"ll":[re,im],"ur":[re,im]} or {"center":[re,im],"zoom":z}
+//    set_iterations  {"iterations":n}
+//    save_orbit      {"c":[re,im]}
+//    get_orbit       {"c":[re,im]}, the iterates until the orbit escapes
+//    screenshot      {"hud":false} to leave out the text, answered once it's written
+//    get_state       the view, iteration limit and saved orbits
+//A request without an id is a notification: it's carried out, but nothing is
+//sent back, not even an error.  An array of requests is a batch, answered with
+//one line holding the array of responses to those that weren't notifications.
+//Each connection is served on its own thread, which hands the commands to the
+//main loop and wakes it up to carry them out.  Scripts send theirs the same way.
+use num::Complex;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use log::{debug, info, warn};
+use orbits::action::Action;
+use orbits::app::{App, Effects, Task};
+use orbits::auto_iterations::MAX_ITERATIONS;
+use orbits::complex_bbox::ComplexBBox;
+use orbits::orbit::calc_orbits;
+
+use crate::cli;
+
+//JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const FAILED: i64 = -32000;
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    SetView(ComplexBBox),
+    SetIterations(u32),
+    SaveOrbit(Complex<f64>),
+    GetOrbit(Complex<f64>),
+    Screenshot { hud: bool },
+    GetState,
+    Line(String), //from --stdin, see command_line.rs
+    //only from scripts
+    Action(Action),
+    Run(Task),
+    Print(String),
+    ScriptDone(Result<(), String>),
+    //from work main hands to other threads
+    Status(Option<String>), //how it's going, None once it's done
+}
+
+pub type Reply = Sender<Result<Value, String>>;
+
+//Tells the main loop there are requests waiting
+pub type Wake = Arc<dyn Fn() + Send + Sync>;
+
+pub struct Request {
+    pub command: Command,
+    pub reply: Reply,
+}
+
+//Where other threads send their commands
+#[derive(Clone)]
+pub struct Link {
+    pub requests: Sender<Request>,
+    pub wake: Wake,
+}
+
+impl Link {
+    //Hand the main loop a command without waiting for it to be carried out
+    pub fn notify(&self, command: Command) {
+        let (reply, _) = mpsc::channel();
+        if self.requests.send(Request { command, reply }).is_ok() {
+            (self.wake)();
+        }
+    }
+}
+
+fn complex_param(params: &Value, name: &str) -> Result<Complex<f64>, String> {
+    match params
+        .get(name)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+    {
+        Some([re, im]) => match (re.as_f64(), im.as_f64()) {
+            (Some(re), Some(im)) if re.is_finite() && im.is_finite() => Ok(Complex::new(re, im)),
+            _ => Err(format!("\"{}\" must be two finite numbers", name)),
+        },
+        _ => Err(format!("\"{}\" must be [re, im]", name)),
+    }
+}
+
+fn parse_view(params: &Value) -> Result<ComplexBBox, String> {
+    if params.get("ll").is_some() || params.get("ur").is_some() {
+        return cli::bbox(complex_param(params, "ll")?, complex_param(params, "ur")?);
+    }
+    let center = complex_param(params, "center")?;
+    let zoom = match params.get("zoom") {
+        None => 1.0,
+        Some(zoom) => match zoom.as_f64() {
+            Some(zoom) if zoom.is_finite() && zoom > 0.0 => zoom,
+            _ => return Err("\"zoom\" must be a positive number".to_string()),
+        },
+    };
+    Ok(cli::centered(Some(center), Some(zoom)))
+}
+
+//The command in a request, or the JSON-RPC error code and message
+pub fn parse(method: &str, params: &Value) -> Result<Command, (i64, String)> {
+    let invalid = |e| (INVALID_PARAMS, e);
+    Ok(match method {
+        "set_view" => Command::SetView(parse_view(params).map_err(invalid)?),
+        "set_iterations" => match params.get("iterations").and_then(Value::as_u64) {
+            Some(n) if (1..=MAX_ITERATIONS as u64).contains(&n) => Command::SetIterations(n as u32),
+            _ => {
+                return Err(invalid(format!(
+                    "\"iterations\" must be a whole number from 1 to {}",
+                    MAX_ITERATIONS
+                )))
+            }
+        },
+        "save_orbit" => Command::SaveOrbit(complex_param(params, "c").map_err(invalid)?),
+        "get_orbit" => Command::GetOrbit(complex_param(params, "c").map_err(invalid)?),
+        "screenshot" => Command::Screenshot {
+            hud: match params.get("hud") {
+                None => true,
+                Some(hud) => hud
+                    .as_bool()
+                    .ok_or_else(|| invalid("\"hud\" must be true or false".to_string()))?,
+            },
+        },
+        "get_state" => Command::GetState,
+        _ => return Err((METHOD_NOT_FOUND, format!("unknown method \"{}\"", method))),
+    })
+}
+
+//Carry out a command on the state.  Screenshots are only asked for here, main
+//answers once it has taken them.
+pub fn execute(app: &mut App, command: Command) -> (Value, Effects) {
+    let pair = |c: Complex<f64>| json!([c.re, c.im]);
+    match command {
+        Command::SetView(view) => {
+            let effects = app.set_view(view);
+            (app.state(), effects)
+        }
+        Command::SetIterations(iterations) => {
+            let effects = app.set_iterations(iterations);
+            (app.state(), effects)
+        }
+        Command::SaveOrbit(c) => {
+            app.saved_orbits.push(c);
+            (
+                json!({ "saved": app.saved_orbits.len() }),
+                Effects::default(),
+            )
+        }
+        Command::GetOrbit(c) => {
+            let orbit: Vec<Value> = calc_orbits(c, &app.bailout).into_iter().map(pair).collect();
+            (json!({ "orbit": orbit }), Effects::default())
+        }
+        Command::Screenshot { hud } => (
+            Value::Null,
+            Effects {
+                task: Some(Task::Screenshot { hud }),
+                ..Effects::default()
+            },
+        ),
+        Command::GetState => (app.state(), Effects::default()),
+        Command::Line(line) => {
+            let effects = app.run_command(&line);
+            (app.state(), effects)
+        }
+        Command::Action(action) => {
+            let effects = app.handle(action);
+            (app.state(), effects)
+        }
+        Command::Run(task) => (
+            Value::Null,
+            Effects {
+                task: Some(task),
+                ..Effects::default()
+            },
+        ),
+        Command::Print(text) => {
+            info!(target: "script", "{}", text);
+            app.console.print(&text);
+            (Value::Null, Effects::default())
+        }
+        Command::Status(status) => {
+            app.status = status;
+            (Value::Null, Effects::default())
+        }
+        Command::ScriptDone(result) => {
+            app.running_scripts = app.running_scripts.saturating_sub(1);
+            if let Err(e) = result {
+                warn!(target: "script", "{}", e);
+                app.console.print(&e);
+            }
+            (Value::Null, Effects::default())
+        }
+    }
+}
+
+//Have the main loop carry out a command and wait for the result, None once
+//the viewer has gone
+pub fn call(
+    requests: &Sender<Request>,
+    wake: &dyn Fn(),
+    command: Command,
+) -> Option<Result<Value, String>> {
+    let (reply, result) = mpsc::channel();
+    requests.send(Request { command, reply }).ok()?;
+    wake();
+    result.recv().ok()
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+//The viewer has gone, with no one left to carry out commands
+#[derive(Debug)]
+struct Gone;
+
+//The response to one request, with the command run by whoever holds the other
+//end of `requests`.  None for a notification.
+fn respond_to(
+    request: &Value,
+    requests: &Sender<Request>,
+    wake: &dyn Fn(),
+) -> Result<Option<Value>, Gone> {
+    let id = request.get("id").cloned();
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            //can't tell whether it was meant as a notification, so always answer
+            let message = "expected {\"jsonrpc\":\"2.0\",\"method\":...}".to_string();
+            return Ok(Some(error(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                message,
+            )));
+        }
+    };
+    let params = request.get("params").cloned().unwrap_or(json!({}));
+    let result = match parse(method, &params) {
+        Ok(command) => {
+            debug!(target: "control", "{:?}", command);
+            call(requests, wake, command)
+                .ok_or(Gone)?
+                .map_err(|message| (FAILED, message))
+        }
+        Err(e) => Err(e),
+    };
+    Ok(id.map(|id| match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error(id, code, message),
+    }))
+}
+
+//The response to one line, a request or a batch of them.  None when there's
+//nothing to send back.
+fn respond(line: &str, requests: &Sender<Request>, wake: &dyn Fn()) -> Result<Option<Value>, Gone> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return Ok(Some(error(Value::Null, PARSE_ERROR, e.to_string()))),
+    };
+    match request {
+        Value::Array(batch) if batch.is_empty() => {
+            let message = "empty batch".to_string();
+            Ok(Some(error(Value::Null, INVALID_REQUEST, message)))
+        }
+        Value::Array(batch) => {
+            let mut responses = Vec::new();
+            for request in &batch {
+                responses.extend(respond_to(request, requests, wake)?);
+            }
+            Ok(if responses.is_empty() {
+                None
+            } else {
+                Some(Value::Array(responses))
+            })
+        }
+        request => respond_to(&request, requests, wake),
+    }
+}
+
+fn serve(stream: TcpStream, requests: Sender<Request>, wake: &dyn Fn()) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match respond(&line, &requests, wake) {
+            Ok(Some(response)) => writeln!(writer, "{}", response)?,
+            Ok(None) => {}
+            Err(Gone) => break,
+        }
+    }
+    Ok(())
+}
+
+//Accept connections on `addr`, passing their commands on to `requests` and
+//calling `wake` after each one.  Returns the address, for when `addr` asks for
+//any free port.
+pub fn listen(addr: SocketAddr, requests: Sender<Request>, wake: Wake) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!(target: "control", "Couldn't accept a connection: {}", e);
+                    continue;
+                }
+            };
+            let (requests, wake) = (requests.clone(), wake.clone());
+            thread::spawn(move || {
+                let peer = stream.peer_addr().ok();
+                info!(target: "control", "Connection from {:?}", peer);
+                if let Err(e) = serve(stream, requests, &*wake) {
+                    debug!(target: "control", "Connection from {:?} ended: {}", peer, e);
+                }
+            });
+        }
+    });
+    Ok(addr)
+}
+
+//Pass the lines typed on stdin to the main loop until it ends
+pub fn read_stdin(requests: Sender<Request>, wake: Wake) {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    debug!(target: "command", "Stopped reading stdin: {}", e);
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if call(&requests, &*wake, Command::Line(line)).is_none() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        App::new(cli::centered(None, None), 50, (200, 200))
+    }
+
+    #[test]
+    fn params_are_checked() {
+        let params = |text: &str| serde_json::from_str::<Value>(text).unwrap();
+        match parse("set_view", &params(r#"{"center":[-0.75,0.1],"zoom":4}"#)) {
+            Ok(Command::SetView(view)) => {
+                assert_eq!(view.ll, Complex::new(-1.0, -0.15));
+                assert_eq!(view.ur, Complex::new(-0.5, 0.35));
+            }
+            other => panic!("{:?}", other),
+        }
+        let code = |method, text| parse(method, &params(text)).unwrap_err().0;
+        assert_eq!(
+            code("set_view", r#"{"ll":[1,0],"ur":[0,1]}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code("set_view", r#"{"center":[0,0],"zoom":0}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code("set_iterations", r#"{"iterations":0}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(code("save_orbit", r#"{"c":[0]}"#), INVALID_PARAMS);
+        assert_eq!(code("rotate", "{}"), METHOD_NOT_FOUND);
+        assert!(matches!(
+            parse("screenshot", &json!({})),
+            Ok(Command::Screenshot { hud: true })
+        ));
+    }
+
+    #[test]
+    fn notifications_get_no_reply_and_batches_one() {
+        let (sender, requests) = mpsc::channel();
+        let client = thread::spawn(move || {
+            let respond = |line: &str| respond(line, &sender, &|| {}).unwrap();
+            vec![
+                respond(
+                    r#"{"jsonrpc":"2.0","method":"set_iterations","params":{"iterations":300}}"#,
+                ),
+                respond(r#"{"jsonrpc":"2.0","method":"jump"}"#),
+                respond(
+                    r#"[{"jsonrpc":"2.0","id":1,"method":"get_state"},
+                        {"jsonrpc":"2.0","method":"set_iterations","params":{"iterations":400}},
+                        5]"#,
+                ),
+                respond(
+                    r#"[{"jsonrpc":"2.0","method":"set_iterations","params":{"iterations":0}}]"#,
+                ),
+                respond("[]"),
+            ]
+        });
+
+        let mut app = app();
+        for request in requests {
+            let (result, _) = execute(&mut app, request.command);
+            request.reply.send(Ok(result)).unwrap();
+        }
+        let responses = client.join().unwrap();
+
+        assert_eq!(responses[0], None);
+        assert_eq!(responses[1], None);
+        let batch = responses[2].as_ref().unwrap().as_array().unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0]["id"], 1);
+        assert_eq!(batch[0]["result"]["iterations"], 300);
+        assert_eq!(batch[1]["id"], Value::Null);
+        assert_eq!(batch[1]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(responses[3], None);
+        assert_eq!(
+            responses[4].as_ref().unwrap()["error"]["code"],
+            INVALID_REQUEST
+        );
+        assert_eq!(app.settings.iterations, 400);
+    }
+
+    //A client on a real socket, with this thread standing in for the main loop
+    #[test]
+    fn a_local_client_drives_the_state() {
+        let (sender, requests) = mpsc::channel();
+        let addr = listen("127.0.0.1:0".parse().unwrap(), sender, Arc::new(|| {})).unwrap();
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut call = |request: &str| -> Value {
+                writeln!(writer, "{}", request).unwrap();
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                serde_json::from_str(&line).unwrap()
+            };
+            let responses = vec![
+                call(
+                    r#"{"jsonrpc":"2.0","id":1,"method":"set_view","params":{"ll":[-2,-1],"ur":[0,1]}}"#,
+                ),
+                call(
+                    r#"{"jsonrpc":"2.0","id":2,"method":"set_iterations","params":{"iterations":200}}"#,
+                ),
+                call(r#"{"jsonrpc":"2.0","id":3,"method":"save_orbit","params":{"c":[-1,0]}}"#),
+                call(r#"{"jsonrpc":"2.0","id":4,"method":"get_orbit","params":{"c":[-1,0]}}"#),
+                call(r#"{"jsonrpc":"2.0","id":"last","method":"get_state"}"#),
+                call(r#"{"jsonrpc":"2.0","id":6,"method":"jump"}"#),
+                call("not json"),
+            ];
+            responses
+        });
+
+        let mut app = app();
+        let mut effects = Effects::default();
+        for _ in 0..5 {
+            let request = requests.recv().unwrap();
+            let (result, more) = execute(&mut app, request.command);
+            effects = effects.and(more);
+            request.reply.send(Ok(result)).unwrap();
+        }
+        let responses = client.join().unwrap();
+        assert!(effects.render);
+
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["view"]["ll"], json!([-2.0, -1.0]));
+        assert_eq!(responses[1]["result"]["iterations"], 200);
+        assert_eq!(responses[2]["result"], json!({ "saved": 1 }));
+        //-1 is a period 2 cycle, 0 -> -1 -> 0 ...
+        let orbit = responses[3]["result"]["orbit"].as_array().unwrap();
+        assert_eq!(
+            orbit[..3],
+            [json!([0.0, 0.0]), json!([-1.0, 0.0]), json!([0.0, 0.0])]
+        );
+        assert_eq!(
+            responses[4],
+            json!({ "jsonrpc": "2.0", "id": "last", "result": {
+                "view": { "ll": [-2.0, -1.0], "ur": [0.0, 1.0] },
+                "iterations": 200,
+                "saved_orbits": [[-1.0, 0.0]],
+            }})
+        );
+        assert_eq!(responses[5]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[6]["error"]["code"], PARSE_ERROR);
+        assert_eq!(app.settings.iterations, 200);
+        assert!(!app.settings.auto_iterations);
+    }
+}
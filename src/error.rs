@@ -0,0 +1,91 @@
+//Everything that can stop the viewer.  Most SDL calls report errors as plain
+//strings, which callers wrap in the variant saying where they came from.
+use sdl2::render::TextureValueError;
+use sdl2::ttf::{FontError, InitError};
+use sdl2::video::WindowBuildError;
+use sdl2::IntegerOrSdlError;
+use std::fmt;
+
+pub enum Error {
+    Sdl(String),
+    Ttf(String),
+    Io(std::io::Error),
+    Config(String),
+    Replay(String),
+    Script(String),
+    Open(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Sdl(e) => write!(f, "SDL error: {}", e),
+            Error::Ttf(e) => write!(f, "font error: {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Replay(e) => write!(f, "replay error: {}", e),
+            Error::Script(e) => write!(f, "script error: {}", e),
+            Error::Open(e) => write!(f, "couldn't open {}", e),
+        }
+    }
+}
+
+//main prints the error it returns with Debug, so make that readable too
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<WindowBuildError> for Error {
+    fn from(e: WindowBuildError) -> Error {
+        Error::Sdl(e.to_string())
+    }
+}
+
+impl From<IntegerOrSdlError> for Error {
+    fn from(e: IntegerOrSdlError) -> Error {
+        Error::Sdl(e.to_string())
+    }
+}
+
+impl From<TextureValueError> for Error {
+    fn from(e: TextureValueError) -> Error {
+        Error::Sdl(e.to_string())
+    }
+}
+
+impl From<FontError> for Error {
+    fn from(e: FontError) -> Error {
+        Error::Ttf(e.to_string())
+    }
+}
+
+impl From<InitError> for Error {
+    fn from(e: InitError) -> Error {
+        Error::Ttf(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_say_where_they_came_from() {
+        let error = Error::Sdl("no renderer".to_string());
+        assert_eq!(error.to_string(), "SDL error: no renderer");
+        let error: Error = std::io::Error::new(std::io::ErrorKind::NotFound, "gone").into();
+        assert_eq!(format!("{:?}", error), "I/O error: gone");
+        let error = Error::Config("keys: unknown key \"Hyper\"".to_string());
+        assert_eq!(error.to_string(), "keys: unknown key \"Hyper\"");
+    }
+}
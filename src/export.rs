@@ -0,0 +1,122 @@
+//Numeric orbit data for use in other tools
+use crate::orbit::{orbit_rows, Bailout, OrbitRow};
+use num::Complex;
+use serde::Serialize;
+use std::fmt::Write;
+
+#[derive(Serialize)]
+struct Parameter {
+    re: f64,
+    im: f64,
+}
+
+#[derive(Serialize)]
+struct ExportedOrbit {
+    label: String,
+    c: Parameter,
+    escaped: bool,
+    points: Vec<OrbitRow>,
+}
+
+#[derive(Serialize)]
+struct Export<'a> {
+    bailout: &'a Bailout,
+    orbits: Vec<ExportedOrbit>,
+}
+
+//"current" for the orbit under the cursor, "saved N" for the saved ones
+fn labelled(current: Option<Complex<f64>>, saved: &[Complex<f64>]) -> Vec<(String, Complex<f64>)> {
+    let current = current.map(|c| ("current".to_string(), c));
+    let saved = saved
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (format!("saved {}", i + 1), *c));
+    current.into_iter().chain(saved).collect()
+}
+
+pub fn orbits_csv(
+    current: Option<Complex<f64>>,
+    saved: &[Complex<f64>],
+    bailout: &Bailout,
+) -> String {
+    let mut csv = String::new();
+    writeln!(
+        csv,
+        "# bailout radius: {}, max iterations: {}",
+        bailout.radius, bailout.max_iterations
+    )
+    .unwrap();
+    writeln!(csv, "orbit,c_re,c_im,index,re,im,abs,escaped").unwrap();
+    for (label, c) in labelled(current, saved) {
+        for row in orbit_rows(c, bailout) {
+            writeln!(
+                csv,
+                "{},{:e},{:e},{},{:e},{:e},{:e},{}",
+                label, c.re, c.im, row.index, row.re, row.im, row.abs, row.escaped
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+pub fn orbits_json(
+    current: Option<Complex<f64>>,
+    saved: &[Complex<f64>],
+    bailout: &Bailout,
+) -> String {
+    let orbits = labelled(current, saved)
+        .into_iter()
+        .map(|(label, c)| {
+            let points = orbit_rows(c, bailout);
+            ExportedOrbit {
+                label,
+                c: Parameter { re: c.re, im: c.im },
+                escaped: points.last().is_some_and(|row| row.escaped),
+                points,
+            }
+        })
+        .collect();
+    serde_json::to_string_pretty(&Export { bailout, orbits }).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_has_a_row_per_iterate_with_the_escape_flag() {
+        let bailout = Bailout::default();
+        let csv = orbits_csv(Some(Complex::new(1.0, 0.0)), &[], &bailout);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "# bailout radius: 2, max iterations: 50");
+        assert_eq!(lines[1], "orbit,c_re,c_im,index,re,im,abs,escaped");
+        //0, 1, 2, 5 -- the last one is outside the radius
+        assert_eq!(lines.len(), 2 + 4);
+        assert!(lines[2].starts_with("current,1e0,0e0,0,0e0,0e0,0e0,false"));
+        assert!(lines[5].starts_with("current,1e0,0e0,3,5e0,0e0,5e0,true"));
+    }
+
+    #[test]
+    fn json_records_c_and_bailout_for_every_orbit() {
+        let bailout = Bailout {
+            max_iterations: 10,
+            radius: 4.0,
+        };
+        let saved = [Complex::new(-1.0, 0.0), Complex::new(0.5, 0.5)];
+        let json = orbits_json(None, &saved, &bailout);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["bailout"]["radius"], 4.0);
+        assert_eq!(value["bailout"]["max_iterations"], 10);
+        let orbits = value["orbits"].as_array().unwrap();
+        assert_eq!(orbits.len(), 2);
+        assert_eq!(orbits[0]["label"], "saved 1");
+        assert_eq!(orbits[0]["c"]["re"], -1.0);
+        //-1 is periodic: 0, -1, 0, -1, ...
+        assert_eq!(orbits[0]["escaped"], false);
+        assert_eq!(orbits[0]["points"].as_array().unwrap().len(), 11);
+        assert_eq!(orbits[1]["escaped"], true);
+    }
+}
@@ -0,0 +1,25 @@
+//The font is compiled in, so the binary runs from any directory.  The
+//emscripten build still embeds the assets directory for anything else.
+use sdl2::rwops::RWops;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use std::path::Path;
+
+use crate::error::Error;
+
+const DEJAVU_SANS_MONO: &[u8] = include_bytes!("../assets/DejaVuSansMono.ttf");
+
+//The built in font, or the one at `path` if given
+pub fn load<'ttf>(
+    ttf_context: &'ttf Sdl2TtfContext,
+    path: Option<&Path>,
+    size: u16,
+) -> Result<Font<'ttf, 'static>, Error> {
+    match path {
+        Some(path) => ttf_context
+            .load_font(path, size)
+            .map_err(|e| Error::Ttf(format!("couldn't load {}: {}", path.display(), e))),
+        None => RWops::from_bytes(DEJAVU_SANS_MONO)
+            .and_then(|rwops| ttf_context.load_font_from_rwops(rwops, size))
+            .map_err(|e| Error::Ttf(format!("couldn't load the built in font: {}", e))),
+    }
+}
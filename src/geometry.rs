@@ -0,0 +1,84 @@
+//Pixel positions and areas on the window, with the parts of SDL's Point and
+//Rect the viewer uses, so the library doesn't need SDL.  main converts.
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl Point {
+    pub fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    pub fn x(self) -> i32 {
+        self.x
+    }
+
+    pub fn y(self) -> i32 {
+        self.y
+    }
+
+    pub fn offset(self, dx: i32, dy: i32) -> Point {
+        Point::new(self.x + dx, self.y + dy)
+    }
+}
+
+//top left corner and size
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            w: width,
+            h: height,
+        }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.w
+    }
+
+    pub fn height(&self) -> u32 {
+        self.h
+    }
+
+    pub fn set_x(&mut self, x: i32) {
+        self.x = x;
+    }
+
+    pub fn set_y(&mut self, y: i32) {
+        self.y = y;
+    }
+
+    pub fn set_width(&mut self, width: u32) {
+        self.w = width;
+    }
+
+    pub fn set_height(&mut self, height: u32) {
+        self.h = height;
+    }
+
+    //move so the middle is at `p`, rounding like SDL
+    pub fn center_on(&mut self, p: Point) {
+        self.x = p.x - self.w as i32 / 2;
+        self.y = p.y - self.h as i32 / 2;
+    }
+}
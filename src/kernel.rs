@@ -0,0 +1,165 @@
+//Escape-time kernels for the background image
+use crate::complex_bbox::ComplexBBox;
+use num::Complex;
+use rayon::prelude::*;
+use std::convert::TryInto;
+
+const BAILOUT_SQR: f64 = 4.0;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Kernel {
+    Scalar,
+    #[default]
+    Packed4,
+    Packed8,
+}
+
+impl Kernel {
+    pub const ALL: [Kernel; 3] = [Kernel::Scalar, Kernel::Packed4, Kernel::Packed8];
+
+    pub fn next(self) -> Kernel {
+        match self {
+            Kernel::Scalar => Kernel::Packed4,
+            Kernel::Packed4 => Kernel::Packed8,
+            Kernel::Packed8 => Kernel::Scalar,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kernel::Scalar => "scalar",
+            Kernel::Packed4 => "packed4",
+            Kernel::Packed8 => "packed8",
+        }
+    }
+}
+
+//Number of iterations before z escapes, or `iter` if it never does
+pub fn escape_time(c: Complex<f64>, iter: u32) -> u32 {
+    let mut z = Complex::<f64> { re: 0.0, im: 0.0 };
+
+    for i in 0..iter {
+        z = z * z + c;
+        if z.norm_sqr() > BAILOUT_SQR {
+            return i;
+        }
+    }
+    iter
+}
+
+//Same as escape_time, but for N points at once.  Each lane keeps its own
+//escape mask; escaped lanes are frozen while the others keep iterating.
+//The arithmetic mirrors Complex's `z * z + c` exactly so both paths agree
+//bit-for-bit.
+pub fn escape_time_lanes<const N: usize>(cre: &[f64; N], cim: &[f64; N], iter: u32) -> [u32; N] {
+    let mut zre = [0.0_f64; N];
+    let mut zim = [0.0_f64; N];
+    let mut active = [true; N];
+    let mut counts = [iter; N];
+
+    for i in 0..iter {
+        let mut any_active = false;
+        for l in 0..N {
+            let re = zre[l] * zre[l] - zim[l] * zim[l] + cre[l];
+            let im = zre[l] * zim[l] + zim[l] * zre[l] + cim[l];
+            let escaped = active[l] & (re * re + im * im > BAILOUT_SQR);
+
+            zre[l] = if active[l] { re } else { zre[l] };
+            zim[l] = if active[l] { im } else { zim[l] };
+            counts[l] = if escaped { i } else { counts[l] };
+            active[l] &= !escaped;
+            any_active |= active[l];
+        }
+        if !any_active {
+            break;
+        }
+    }
+    counts
+}
+
+fn escape_row_lanes<const N: usize>(
+    view: &ComplexBBox,
+    y: i32,
+    w: i32,
+    h: i32,
+    iter: u32,
+    out: &mut [u32],
+) {
+    for (chunk_idx, chunk) in out.chunks_mut(N).enumerate() {
+        let x0 = (chunk_idx * N) as i32;
+        let mut cre = [0.0; N];
+        let mut cim = [0.0; N];
+        for l in 0..N {
+            //pad a short final chunk by repeating the last pixel
+            let x = (x0 + l as i32).min(w - 1);
+            let c = view.screen_to_complex(x, y, w, h);
+            cre[l] = c.re;
+            cim[l] = c.im;
+        }
+        let counts = escape_time_lanes::<N>(&cre, &cim, iter);
+        chunk.copy_from_slice(&counts[..chunk.len()]);
+    }
+}
+
+//Fill `out` (one row of `w` pixels) with escape times
+pub fn escape_row(
+    kernel: Kernel,
+    view: &ComplexBBox,
+    y: i32,
+    w: i32,
+    h: i32,
+    iter: u32,
+    out: &mut [u32],
+) {
+    match kernel {
+        Kernel::Scalar => {
+            for (x, count) in out.iter_mut().enumerate() {
+                let c = view.screen_to_complex(x as i32, y, w, h);
+                *count = escape_time(c, iter);
+            }
+        }
+        Kernel::Packed4 => escape_row_lanes::<4>(view, y, w, h, iter, out),
+        Kernel::Packed8 => escape_row_lanes::<8>(view, y, w, h, iter, out),
+    }
+}
+
+//Escape times for every pixel of a `w` x `h` image of `view`, row-major
+pub fn render_iterations(
+    view: &ComplexBBox,
+    w: usize,
+    h: usize,
+    iter: u32,
+    kernel: Kernel,
+) -> Vec<u32> {
+    let mut counts = vec![0; w * h];
+    let (wi, hi): (i32, i32) = (w.try_into().unwrap(), h.try_into().unwrap());
+
+    counts
+        .par_chunks_mut(w.max(1))
+        .enumerate()
+        .for_each(|(y, row)| escape_row(kernel, view, y as i32, wi, hi, iter, row));
+    counts
+}
+
+//sRGB (red, green, blue) for an escape time
+pub fn palette(count: u32, iter: u32) -> (u8, u8, u8) {
+    let color = if count < iter { 255 } else { 0 };
+    (color, color, color)
+}
+
+//Write escape times into a 4-bytes-per-pixel ARGB8888 buffer
+pub fn colorize(counts: &[u32], iter: u32, w: usize, pixel_buffer: &mut [u8], pitch: usize) {
+    pixel_buffer
+        .par_chunks_mut(pitch)
+        .zip(counts.par_chunks(w.max(1)))
+        .for_each(|(buffer, row)| {
+            for (x, count) in row.iter().enumerate() {
+                let (red, green, blue) = palette(*count, iter);
+                let offset: usize = x * 4;
+                buffer[offset] = blue;
+                buffer[offset + 1] = green;
+                buffer[offset + 2] = red;
+                buffer[offset + 3] = 255; //Alpha
+            }
+        });
+}
@@ -0,0 +1,326 @@
+//The keys and mouse buttons that ask for each Action.  Keys can be rebound
+//from the config file.
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use std::convert::TryFrom;
+
+pub use orbits::action::Action;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    Key(Keycode, bool), //with shift held?
+    Mouse(MouseButton),
+}
+
+const fn key(key: Keycode) -> Input {
+    Input::Key(key, false)
+}
+
+const fn shift(key: Keycode) -> Input {
+    Input::Key(key, true)
+}
+
+const DEFAULTS: [(Input, Action); 54] = [
+    (key(Keycode::Q), Action::Quit),
+    (key(Keycode::C), Action::ToggleCoords),
+    (key(Keycode::H), Action::TogglePerfHud),
+    (key(Keycode::I), Action::MoreIterations),
+    (shift(Keycode::I), Action::FewerIterations),
+    (key(Keycode::U), Action::ToggleAutoIterations),
+    (key(Keycode::S), Action::NextAntiAlias),
+    (key(Keycode::L), Action::NextLineThickness),
+    (shift(Keycode::L), Action::ToggleLineAntiAlias),
+    (key(Keycode::D), Action::ToggleMarkers),
+    (key(Keycode::T), Action::NextNumbering),
+    (key(Keycode::G), Action::ToggleGradient),
+    (key(Keycode::P), Action::TogglePlayback),
+    (key(Keycode::Return), Action::TogglePause),
+    (key(Keycode::N), Action::StepForward),
+    (key(Keycode::B), Action::StepBack),
+    (key(Keycode::PageUp), Action::Faster),
+    (key(Keycode::PageDown), Action::Slower),
+    (key(Keycode::K), Action::NextKernel),
+    (key(Keycode::R), Action::NextStrategy),
+    (key(Keycode::F), Action::ToggleFullscreen),
+    (key(Keycode::Escape), Action::ExitFullscreen),
+    (key(Keycode::Home), Action::Home),
+    (key(Keycode::Equals), Action::ZoomIn),
+    (key(Keycode::KpPlus), Action::ZoomIn),
+    (key(Keycode::Minus), Action::ZoomOut),
+    (key(Keycode::KpMinus), Action::ZoomOut),
+    (key(Keycode::Left), Action::CursorLeft),
+    (key(Keycode::Kp4), Action::CursorLeft),
+    (key(Keycode::Right), Action::CursorRight),
+    (key(Keycode::Kp6), Action::CursorRight),
+    (key(Keycode::Up), Action::CursorUp),
+    (key(Keycode::Kp8), Action::CursorUp),
+    (key(Keycode::Down), Action::CursorDown),
+    (key(Keycode::Kp2), Action::CursorDown),
+    (key(Keycode::M), Action::ToggleMenu),
+    (key(Keycode::Space), Action::SaveOrbit),
+    (Input::Mouse(MouseButton::Right), Action::SaveOrbit),
+    (key(Keycode::Backspace), Action::ClearOrbits),
+    (key(Keycode::E), Action::ExportCsv),
+    (key(Keycode::J), Action::ExportJson),
+    (key(Keycode::V), Action::ExportSvg),
+    (shift(Keycode::V), Action::ExportSvgWithAxes),
+    (key(Keycode::F12), Action::Screenshot),
+    (key(Keycode::PrintScreen), Action::Screenshot),
+    (shift(Keycode::F12), Action::ScreenshotWithoutHud),
+    (shift(Keycode::PrintScreen), Action::ScreenshotWithoutHud),
+    (key(Keycode::X), Action::ExportLocation),
+    (key(Keycode::Z), Action::ZoomSequence),
+    (shift(Keycode::Z), Action::ZoomSequenceWithOrbit),
+    (key(Keycode::Backquote), Action::ToggleConsole),
+    (shift(Keycode::Semicolon), Action::CommandLine),
+    (key(Keycode::Colon), Action::CommandLine),
+    (key(Keycode::A), Action::About),
+];
+
+//keys with names longer than one character, SDL's own names need SDL running
+const NAMED_KEYS: [(&str, Keycode); 38] = [
+    ("Space", Keycode::Space),
+    ("Return", Keycode::Return),
+    ("Backspace", Keycode::Backspace),
+    ("Tab", Keycode::Tab),
+    ("Escape", Keycode::Escape),
+    ("Delete", Keycode::Delete),
+    ("Insert", Keycode::Insert),
+    ("Home", Keycode::Home),
+    ("End", Keycode::End),
+    ("PageUp", Keycode::PageUp),
+    ("PageDown", Keycode::PageDown),
+    ("Up", Keycode::Up),
+    ("Down", Keycode::Down),
+    ("Left", Keycode::Left),
+    ("Right", Keycode::Right),
+    ("PrintScreen", Keycode::PrintScreen),
+    ("F1", Keycode::F1),
+    ("F2", Keycode::F2),
+    ("F3", Keycode::F3),
+    ("F4", Keycode::F4),
+    ("F5", Keycode::F5),
+    ("F6", Keycode::F6),
+    ("F7", Keycode::F7),
+    ("F8", Keycode::F8),
+    ("F9", Keycode::F9),
+    ("F10", Keycode::F10),
+    ("F11", Keycode::F11),
+    ("F12", Keycode::F12),
+    ("KpPlus", Keycode::KpPlus),
+    ("KpMinus", Keycode::KpMinus),
+    ("Kp0", Keycode::Kp0),
+    ("Kp2", Keycode::Kp2),
+    ("Kp4", Keycode::Kp4),
+    ("Kp5", Keycode::Kp5),
+    ("Kp6", Keycode::Kp6),
+    ("Kp8", Keycode::Kp8),
+    ("KpEnter", Keycode::KpEnter),
+    ("KpPeriod", Keycode::KpPeriod),
+];
+
+//the left button pans and picks menu items, so it can't be bound
+const MOUSE_BUTTONS: [(&str, MouseButton); 4] = [
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+    ("MouseX1", MouseButton::X1),
+    ("MouseX2", MouseButton::X2),
+];
+
+fn names<T>(table: &[(&str, T)]) -> String {
+    table.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", ")
+}
+
+//"q", "Q", ",", "PageUp", "f12"...
+pub fn parse_key(name: &str) -> Result<Keycode, String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        //SDL keycodes of printable keys are their (unshifted) ASCII codes
+        if c.is_ascii_graphic() {
+            if let Some(key) = Keycode::from_i32(c.to_ascii_lowercase() as i32) {
+                return Ok(key);
+            }
+        }
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+        .ok_or(format!(
+            "unknown key \"{}\", use a single character, {} or {}",
+            name,
+            names(&NAMED_KEYS),
+            names(&MOUSE_BUTTONS)
+        ))
+}
+
+pub fn key_name(key: Keycode) -> String {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, k)| *k == key) {
+        return name.to_string();
+    }
+    match u8::try_from(key as i32) {
+        Ok(c) if c.is_ascii_graphic() => (c as char).to_ascii_uppercase().to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+impl Input {
+    pub fn key(key: Keycode, keymod: Mod) -> Input {
+        Input::Key(key, keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD))
+    }
+
+    //"Q", "Shift+F12", "MouseRight"...
+    pub fn parse(name: &str) -> Result<Input, String> {
+        if let Some((_, button)) = MOUSE_BUTTONS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            return Ok(Input::Mouse(*button));
+        }
+        match name.split_once('+') {
+            Some((modifier, key)) if modifier.eq_ignore_ascii_case("shift") && !key.is_empty() => {
+                Ok(Input::Key(parse_key(key)?, true))
+            }
+            _ => Ok(Input::Key(parse_key(name)?, false)),
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Input::Key(key, false) => key_name(key),
+            Input::Key(key, true) => format!("Shift+{}", key_name(key)),
+            Input::Mouse(button) => MOUSE_BUTTONS
+                .iter()
+                .find(|(_, b)| *b == button)
+                .map(|(n, _)| n.to_string())
+                .unwrap_or_else(|| format!("{:?}", button)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(Input, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            bindings: DEFAULTS.to_vec(),
+        }
+    }
+}
+
+impl Keymap {
+    //Replace the inputs for the action called `name`, an empty list unbinds it
+    pub fn bind(&mut self, name: &str, inputs: &[String]) -> Result<(), String> {
+        let action = Action::from_name(name)?;
+        let inputs = inputs
+            .iter()
+            .map(|i| Input::parse(i))
+            .collect::<Result<Vec<Input>, String>>()?;
+        self.bindings.retain(|(_, a)| *a != action);
+        self.bindings
+            .extend(inputs.into_iter().map(|i| (i, action)));
+        Ok(())
+    }
+
+    //no key may do two things
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, (input, action)) in self.bindings.iter().enumerate() {
+            if let Some((_, other)) = self.bindings[i + 1..]
+                .iter()
+                .find(|(other, a)| other == input && a != action)
+            {
+                return Err(format!(
+                    "\"{}\" and \"{}\" are both bound to {}",
+                    action.name(),
+                    other.name(),
+                    input.name()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    //Keys pressed with shift fall back to the plain key, unless shift
+    //makes them do something else
+    pub fn action(&self, input: Input) -> Option<Action> {
+        let find = |input| {
+            self.bindings
+                .iter()
+                .find(|(i, _)| *i == input)
+                .map(|(_, a)| *a)
+        };
+        match input {
+            Input::Key(key, true) => find(input).or_else(|| find(Input::Key(key, false))),
+            _ => find(input),
+        }
+    }
+
+    pub fn inputs(&self, action: Action) -> Vec<Input> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(i, _)| *i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_names_round_trip() {
+        for (name, input) in [
+            ("Q", key(Keycode::Q)),
+            (",", key(Keycode::Comma)),
+            ("7", key(Keycode::Num7)),
+            ("PageUp", key(Keycode::PageUp)),
+            ("Shift+F12", shift(Keycode::F12)),
+            ("MouseMiddle", Input::Mouse(MouseButton::Middle)),
+        ] {
+            assert_eq!(Input::parse(name), Ok(input));
+            assert_eq!(input.name(), name);
+        }
+        assert_eq!(Input::parse("q"), Ok(key(Keycode::Q)));
+        assert_eq!(Input::parse("shift+pageup"), Ok(shift(Keycode::PageUp)));
+        assert!(Input::parse("Hyper").unwrap_err().contains("unknown key"));
+        for action in Action::ALL {
+            assert_eq!(Action::from_name(action.name()), Ok(action));
+        }
+    }
+
+    #[test]
+    fn keys_can_be_rebound() {
+        let mut keys = Keymap::default();
+        keys.bind("quit", &["F10".to_string(), "Shift+Q".to_string()])
+            .unwrap();
+        keys.validate().unwrap();
+
+        assert_eq!(keys.action(key(Keycode::F10)), Some(Action::Quit));
+        assert_eq!(keys.action(shift(Keycode::Q)), Some(Action::Quit));
+        assert_eq!(keys.action(key(Keycode::Q)), None);
+        //shift falls back to the plain key, unless bound separately
+        assert_eq!(keys.action(shift(Keycode::C)), Some(Action::ToggleCoords));
+        assert_eq!(
+            keys.action(shift(Keycode::I)),
+            Some(Action::FewerIterations)
+        );
+        assert_eq!(
+            keys.action(Input::Mouse(MouseButton::Right)),
+            Some(Action::SaveOrbit)
+        );
+
+        keys.bind("menu", &["F10".to_string()]).unwrap();
+        assert_eq!(
+            keys.validate(),
+            Err("\"quit\" and \"menu\" are both bound to F10".to_string())
+        );
+        assert!(keys
+            .bind("teleport", &["T".to_string()])
+            .unwrap_err()
+            .contains("unknown action"));
+    }
+}
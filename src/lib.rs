@@ -0,0 +1,21 @@
+//Mandelbrot Orbits -- view geometry, rendering and the viewer's state, shared by the viewer and the benches
+pub mod action;
+pub mod app;
+pub mod auto_iterations;
+pub mod command_line;
+pub mod complex_bbox;
+pub mod console;
+pub mod export;
+pub mod geometry;
+pub mod kernel;
+pub mod location;
+pub mod orbit;
+pub mod orbit_style;
+pub mod playback;
+pub mod png_io;
+pub mod render;
+pub mod screenshot;
+pub mod subdivide;
+pub mod supersample;
+pub mod svg;
+pub mod zoom_sequence;
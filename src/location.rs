@@ -0,0 +1,387 @@
+//Locations shared with other fractal programs: Kalles Fraktaler (.kfr),
+//XaoS (.xpf) and Fractint (.par).  None of them store the window shape the
+//way a bbox does, so reading one needs the aspect ratio (width / height) of
+//the window it will be shown in.
+use crate::auto_iterations::MAX_ITERATIONS;
+use crate::complex_bbox::{bbox, ComplexBBox};
+use num::Complex;
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Kfr,
+    Xpf,
+    Par,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Kfr, Format::Xpf, Format::Par];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Kfr => "kfr",
+            Format::Xpf => "xpf",
+            Format::Par => "par",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|f| f.extension() == extension)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Location {
+    pub view: ComplexBBox,
+    pub iterations: u32,
+}
+
+impl Location {
+    fn center(&self) -> Complex<f64> {
+        (self.view.ll + self.view.ur) / 2.0
+    }
+
+    fn size(&self) -> Complex<f64> {
+        self.view.ur - self.view.ll
+    }
+
+    //view of the given height (in the imaginary direction) around `center`
+    fn around(center: Complex<f64>, height: f64, aspect: f64, iterations: u32) -> Location {
+        let half = Complex::new(height * aspect, height) / 2.0;
+        Location {
+            view: ComplexBBox {
+                ll: center - half,
+                ur: center + half,
+            },
+            iterations,
+        }
+    }
+}
+
+pub fn read(format: Format, text: &str, aspect: f64) -> Result<Location, String> {
+    let location = match format {
+        Format::Kfr => read_kfr(text, aspect),
+        Format::Xpf => read_xpf(text, aspect),
+        Format::Par => read_par(text, aspect),
+    }?;
+    //a center and size far enough out can still make an empty or infinite view
+    bbox(location.view.ll, location.view.ur)?;
+    Ok(location)
+}
+
+pub fn write(format: Format, location: &Location) -> String {
+    match format {
+        Format::Kfr => write_kfr(location),
+        Format::Xpf => write_xpf(location),
+        Format::Par => write_par(location),
+    }
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|e| format!("{} \"{}\": {}", name, value.trim(), e))
+}
+
+fn real(name: &str, value: &str) -> Result<f64, String> {
+    match number::<f64>(name, value)? {
+        x if x.is_finite() => Ok(x),
+        _ => Err(format!(
+            "{} \"{}\" isn't a finite number",
+            name,
+            value.trim()
+        )),
+    }
+}
+
+//the same limits as everywhere else, files from other programs often go higher
+fn iterations(name: &str, value: &str) -> Result<u32, String> {
+    match number::<u32>(name, value)? {
+        n if (1..=MAX_ITERATIONS).contains(&n) => Ok(n),
+        n => Err(format!("{} {} isn't from 1 to {}", name, n, MAX_ITERATIONS)),
+    }
+}
+
+//Kalles Fraktaler: "Key: value" lines.  Zoom 1 shows 4 units vertically.
+const KFR_HEIGHT: f64 = 4.0;
+
+fn read_kfr(text: &str, aspect: f64) -> Result<Location, String> {
+    let get = |key: &str| {
+        text.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(k, _)| k.trim() == key)
+            .map(|(_, v)| v.trim())
+            .ok_or(format!(
+                "no \"{}:\" line, not a Kalles Fraktaler location?",
+                key
+            ))
+    };
+    let re = real("Re", get("Re")?)?;
+    let im = real("Im", get("Im")?)?;
+    let zoom = real("Zoom", get("Zoom")?)?;
+    let iterations = iterations("Iterations", get("Iterations")?)?;
+    if zoom <= 0.0 {
+        return Err(format!("Zoom must be positive, got {}", zoom));
+    }
+    Ok(Location::around(
+        Complex::new(re, im),
+        KFR_HEIGHT / zoom,
+        aspect,
+        iterations,
+    ))
+}
+
+fn write_kfr(location: &Location) -> String {
+    let center = location.center();
+    format!(
+        "Re: {}\r\nIm: {}\r\nZoom: {:e}\r\nIterations: {}\r\n",
+        center.re,
+        center.im,
+        KFR_HEIGHT / location.size().im,
+        location.iterations
+    )
+}
+
+//XaoS: a script of parenthesized commands, "(view re im width height)" with
+//the full width and height of the view
+fn xpf_commands(text: &str) -> Vec<Vec<&str>> {
+    text.lines()
+        .map(|line| line.split(';').next().unwrap_or(""))
+        .flat_map(|line| line.split('('))
+        .filter_map(|command| {
+            let words: Vec<&str> = command
+                .trim_end()
+                .trim_end_matches(')')
+                .split_whitespace()
+                .collect();
+            if words.is_empty() {
+                None
+            } else {
+                Some(words)
+            }
+        })
+        .collect()
+}
+
+fn read_xpf(text: &str, aspect: f64) -> Result<Location, String> {
+    let commands = xpf_commands(text);
+    let find = |name: &str| commands.iter().find(|c| c[0] == name);
+
+    if let Some(formula) = find("formula") {
+        match formula.get(1) {
+            Some(&"'mandel") | Some(&"mandel") => (),
+            other => return Err(format!("unsupported formula {}", other.unwrap_or(&"\"\""))),
+        }
+    }
+    let view = find("view").ok_or("no (view ...) command, not a XaoS location?")?;
+    let (re, im, width, height) = match view[1..] {
+        [re, im, width, height] => (
+            real("view re", re)?,
+            real("view im", im)?,
+            real("view width", width)?,
+            real("view height", height)?,
+        ),
+        _ => {
+            return Err(format!(
+                "(view ...) needs 4 numbers, got {}",
+                view.len() - 1
+            ))
+        }
+    };
+    let iterations = match find("maxiter") {
+        Some(maxiter) => iterations("maxiter", maxiter.get(1).unwrap_or(&""))?,
+        None => 170, //XaoS default
+    };
+    //XaoS keeps the smaller side of the view, like fitting it in the window
+    let height = height.min(width / aspect);
+    Ok(Location::around(
+        Complex::new(re, im),
+        height,
+        aspect,
+        iterations,
+    ))
+}
+
+fn write_xpf(location: &Location) -> String {
+    let (center, size) = (location.center(), location.size());
+    format!(
+        ";Mandelbrot Orbits location\n(initstate)\n(formula 'mandel)\n(view {} {} {} {})\n(maxiter {})\n",
+        center.re, center.im, size.re, size.im, location.iterations
+    )
+}
+
+//Fractint: "name { key=value ... }" entries, either "corners=xmin/xmax/ymin/ymax"
+//or "center-mag=re/im/mag[/xmagfactor]" with magnification 1 showing 2 units
+//vertically.  The first entry is used.
+fn read_par(text: &str, aspect: f64) -> Result<Location, String> {
+    let text: String = text
+        .lines()
+        .map(|line| line.split(';').next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let start = text
+        .find('{')
+        .ok_or("no \"name { ... }\" entry, not a Fractint parameter file?")?;
+    let end = text[start..]
+        .find('}')
+        .ok_or("entry isn't closed with \"}\"")?;
+    let params: Vec<(&str, &str)> = text[start + 1..start + end]
+        .split_whitespace()
+        .filter_map(|param| param.split_once('='))
+        .collect();
+    let get = |key: &str| params.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+
+    let kind = get("type").unwrap_or("mandel");
+    if kind != "mandel" {
+        return Err(format!(
+            "unsupported type={}, only mandel entries can be read",
+            kind
+        ));
+    }
+    let iterations = match get("maxiter") {
+        Some(maxiter) => iterations("maxiter", maxiter)?,
+        None => 150, //Fractint default
+    };
+    let values = |name: &str, value: &str| -> Result<Vec<f64>, String> {
+        value.split('/').map(|v| real(name, v)).collect()
+    };
+
+    if let Some(corners) = get("corners") {
+        match values("corners", corners)?[..] {
+            [xmin, xmax, ymin, ymax] => Ok(Location {
+                view: bbox(Complex::new(xmin, ymin), Complex::new(xmax, ymax))?,
+                iterations,
+            }),
+            _ => Err(
+                "corners= needs xmin/xmax/ymin/ymax (rotated views aren't supported)".to_string(),
+            ),
+        }
+    } else if let Some(center_mag) = get("center-mag") {
+        let v = values("center-mag", center_mag)?;
+        if v.len() < 3 || v[2] <= 0.0 {
+            return Err(
+                "center-mag= needs re/im/magnification with a positive magnification".to_string(),
+            );
+        }
+        let xmagfactor = v.get(3).copied().unwrap_or(1.0);
+        let mut location =
+            Location::around(Complex::new(v[0], v[1]), 2.0 / v[2], aspect, iterations);
+        let half_width = location.size().re / 2.0 / xmagfactor;
+        location.view.ll.re = v[0] - half_width;
+        location.view.ur.re = v[0] + half_width;
+        Ok(location)
+    } else {
+        Err("entry has neither corners= nor center-mag=".to_string())
+    }
+}
+
+fn write_par(location: &Location) -> String {
+    let ComplexBBox { ll, ur } = location.view;
+    format!(
+        "orbits {{ ; Mandelbrot Orbits location\n  reset=2004 type=mandel\n  corners={}/{}/{}/{}\n  maxiter={}\n  }}\n",
+        ll.re, ur.re, ll.im, ur.im, location.iterations
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deep() -> Location {
+        Location {
+            view: ComplexBBox {
+                ll: Complex::new(-0.743643887037159 - 6e-12, 0.131825904205312 - 4.5e-12),
+                ur: Complex::new(-0.743643887037159 + 6e-12, 0.131825904205312 + 4.5e-12),
+            },
+            iterations: 12800,
+        }
+    }
+
+    fn assert_same(a: &Location, b: &Location) {
+        //relative to the view size, plus rounding of the center
+        let size = b.size();
+        let close = |x: f64, y: f64, span: f64| (x - y).abs() <= span * 1e-9 + y.abs() * 1e-15;
+        for (x, y) in [(a.view.ll, b.view.ll), (a.view.ur, b.view.ur)] {
+            assert!(close(x.re, y.re, size.re), "{} != {}", x, y);
+            assert!(close(x.im, y.im, size.im), "{} != {}", x, y);
+        }
+        assert_eq!(a.iterations, b.iterations);
+    }
+
+    #[test]
+    fn locations_round_trip_through_every_format() {
+        let location = deep();
+        let aspect = location.size().re / location.size().im;
+        for format in Format::ALL {
+            let text = write(format, &location);
+            let restored = read(format, &text, aspect)
+                .unwrap_or_else(|e| panic!("{:?}: {}\n{}", format, e, text));
+            assert_same(&restored, &location);
+        }
+    }
+
+    #[test]
+    fn reads_files_written_by_other_programs() {
+        let kfr = "Re: -0.75\r\nIm: 0.1\r\nZoom: 2E1\r\nIterations: 500\r\nIterDiv: 1.000000\r\nSmooth: 1\r\n";
+        let location = read(Format::Kfr, kfr, 2.0).unwrap();
+        assert_same(
+            &location,
+            &Location::around(Complex::new(-0.75, 0.1), 0.2, 2.0, 500),
+        );
+
+        let xpf = ";Position file automatically generated by XaoS 4.2.1\n(initstate)\n(defaultpalette 0)\n(formula 'mandel)\n(view -0.75 0 2.5 2.5)\n(maxiter 1000)\n(angle 0)\n";
+        let location = read(Format::Xpf, xpf, 4.0 / 3.0).unwrap();
+        assert_same(
+            &location,
+            &Location::around(Complex::new(-0.75, 0.0), 1.875, 4.0 / 3.0, 1000),
+        );
+
+        let par = "; comment\nSeahorse         { ; from Fractint\n  reset=2004 type=mandel\n  center-mag=-0.745/0.1/20 params=0/0\n  float=y maxiter=2000 inside=0\n  }\n\nOther { type=julia }\n";
+        let location = read(Format::Par, par, 1.5).unwrap();
+        assert_same(
+            &location,
+            &Location::around(Complex::new(-0.745, 0.1), 0.1, 1.5, 2000),
+        );
+    }
+
+    #[test]
+    fn errors_say_what_is_wrong() {
+        assert!(
+            read(Format::Kfr, "Re: 0\nIm: x\nZoom: 1\nIterations: 5\n", 1.0)
+                .err()
+                .unwrap()
+                .starts_with("Im \"x\"")
+        );
+        assert!(read(Format::Xpf, "(formula 'julia)\n(view 0 0 1 1)\n", 1.0)
+            .err()
+            .unwrap()
+            .contains("julia"));
+        assert!(read(Format::Par, "j { type=julia corners=0/1/0/1 }", 1.0)
+            .err()
+            .unwrap()
+            .contains("type=julia"));
+
+        //nothing the viewer couldn't be asked for any other way
+        let error = |format, text: &str| -> String { read(format, text, 1.0).err().unwrap() };
+        let kfr = |zoom, iterations| {
+            format!("Re: 0\nIm: 0\nZoom: {}\nIterations: {}\n", zoom, iterations)
+        };
+        assert!(error(Format::Kfr, &kfr("1", "1000000")).contains("isn't from 1 to"));
+        assert!(error(Format::Kfr, &kfr("1", "0")).contains("isn't from 1 to"));
+        assert!(error(Format::Kfr, &kfr("nan", "100")).contains("finite"));
+        assert!(error(Format::Kfr, &kfr("1e-320", "100")).contains("finite"));
+        assert!(error(Format::Xpf, "(view 0 inf 1 1)").contains("finite"));
+        assert!(error(Format::Par, "m { corners=1/0/1/0 }").contains("below and left"));
+        assert!(error(Format::Par, "m { corners=0/1/0/1 maxiter=99999999 }").contains("from 1 to"));
+        assert_eq!(Format::from_path(Path::new("a/B.KFR")), Some(Format::Kfr));
+        assert_eq!(Format::from_path(Path::new("a.png")), None);
+    }
+}
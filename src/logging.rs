@@ -0,0 +1,33 @@
+//Leveled logging with a target per part of the viewer:
+//  events  input events as they come in
+//  render  background rendering, resizes and their timings
+//  menu    menu items picked
+//Everything else logs under "orbits".  The level comes from ORBITS_LOG or
+//--log, both in env_logger's syntax, e.g. "debug" or "info,events=trace".
+use env_logger::{Builder, Target, WriteStyle};
+use std::fs::File;
+use std::path::Path;
+
+use crate::error::Error;
+
+const DEFAULT_FILTER: &str = "info";
+
+//Log to stderr, or only to `file` (for bug reports) if given
+pub fn init(filter: Option<&str>, file: Option<&Path>) -> Result<(), Error> {
+    let mut builder = Builder::new();
+    builder.parse_filters(DEFAULT_FILTER);
+    if let Ok(env) = std::env::var("ORBITS_LOG") {
+        builder.parse_filters(&env);
+    }
+    if let Some(filter) = filter {
+        builder.parse_filters(filter);
+    }
+    if let Some(path) = file {
+        let file = File::create(path)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        builder
+            .target(Target::Pipe(Box::new(file)))
+            .write_style(WriteStyle::Never);
+    }
+    builder.try_init().map_err(|e| Error::Config(e.to_string()))
+}
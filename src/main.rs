@@ -1,11 +1,10 @@
 //Mandelbrot Orbits
 use num::Complex;
 use std::convert::TryInto;
-use std::time::Instant;
-
-//#[cfg(not(target_os = "emscripten"))]
-use rayon::prelude::*;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use clap::Parser;
+use log::{debug, info, trace, warn};
 use sdl2;
 use sdl2::event::Event;
 use sdl2::event::WindowEvent;
@@ -15,100 +14,85 @@ use sdl2::mouse::MouseState;
 use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Point;
 use sdl2::rect::Rect;
-use sdl2::render::TextureQuery;
+use sdl2::render::{Canvas, TextureCreator, TextureQuery};
+use sdl2::ttf::Font;
+use sdl2::video::{Window, WindowContext};
+use sdl2::EventPump;
 use std::path::Path;
-extern crate itertools;
-use itertools::Itertools;
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use orbits::app::{self, App, EditKey, Effects, Task};
+use orbits::complex_bbox::ComplexBBox;
+use orbits::export;
+use orbits::geometry;
+use orbits::kernel;
+use orbits::location;
+use orbits::orbit::calc_orbits;
+use orbits::playback;
+use orbits::render::{self, RenderSettings};
+use orbits::screenshot::{self, ViewMetadata};
+use orbits::supersample::{self, AntiAlias};
+use orbits::svg::{self, SvgOptions, SvgOrbit};
+use orbits::zoom_sequence::{self, ZoomSequence};
 
+mod cli;
+mod config;
+mod control;
+mod error;
+mod font;
+mod keymap;
+mod logging;
 mod menu;
+mod orbit_draw;
+mod perf;
+mod recording;
+mod script;
+
+use config::Colors;
+use control::{Command, Request};
+use error::Error;
+use keymap::{Action, Input};
+use perf::{Perf, RenderStats};
+use recording::{Recorded, Recorder, Replay};
+use script::Scripts;
 
 const SDL_TOUCH_MOUSEID: u32 = u32::MAX;
 
 const INITIAL_ITERATIONS: u32 = 50;
+const CONSOLE_LINES: usize = 10; //of output shown above the line being typed
 
-#[derive(Copy, Clone)]
-struct ComplexBBox {
-    ll: Complex<f64>,
-    ur: Complex<f64>,
-}
-
-impl ComplexBBox {
-    fn screen_to_complex(&self, x: i32, y: i32, w: i32, h: i32) -> Complex<f64> {
-        let (x, y, w, h) = (x as f64, y as f64, w as f64, h as f64);
-        let (lower, left) = (self.ll.im, self.ll.re);
-        let (upper, right) = (self.ur.im, self.ur.re);
-
-        Complex {
-            re: (left + (x / w) * (right - left)),
-            im: (upper + (y / h) * (lower - upper)),
-        }
-    }
-
-    fn complex_to_screen(&self, c: Complex<f64>, w: i32, h: i32) -> Point {
-        let Complex { re, im } = c;
-        let (w, h) = (w as f64, h as f64);
-        let (lower, left) = (self.ll.im, self.ll.re);
-        let (upper, right) = (self.ur.im, self.ur.re);
-        let x = ((re - left) * w / (right - left)) as i32;
-        let y = ((im - upper) * h / (lower - upper)) as i32;
-        Point::new(x, y)
-    }
-
-    fn complex_deltas(&self, w: i32, h: i32, dx: i32, dy: i32) -> Complex<f64> {
-        let (w, h) = (w as f64, h as f64);
-        let (dx, dy) = (dx as f64, dy as f64);
-        let left = self.ll.re;
-        let right = self.ur.re;
-        let lower = self.ll.im;
-        let upper = self.ur.im;
-
-        Complex {
-            re: ((dx / w) * (right - left)),
-            im: ((-dy / h) * (upper - lower)),
-        }
-    }
+fn main() -> Result<(), Error> {
+    let args = cli::Args::parse();
+    logging::init(args.log.as_deref(), args.log_file.as_deref())?;
+    let config = config::Config::load(args.config.as_deref()).map_err(Error::Config)?;
+    let (initial_width, initial_height) = args.size.or(config.window_size).unwrap_or((800, 600));
 
-    fn zoom(&self, position: Complex<f64>, scale_factor: f64) -> ComplexBBox {
-        let Complex { re: x, im: y } = position;
-        let new_lower = y - (y - self.ll.im) * scale_factor;
-        let new_upper = new_lower + (self.ur.im - self.ll.im) * scale_factor;
-        let new_left = x - (x - self.ll.re) * scale_factor;
-        let new_right = new_left + (self.ur.re - self.ll.re) * scale_factor;
-
-        ComplexBBox {
-            ll: Complex {
-                re: new_left,
-                im: new_lower,
-            },
-            ur: Complex {
-                re: new_right,
-                im: new_upper,
-            },
-        }
-    }
-}
-
-fn main() -> Result<(), String> {
     #[cfg(target_os = "emscripten")]
     {
         let _ = sdl2::hint::set("SDL_EMSCRIPTEN_ASYNCIFY", "1");
     }
 
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
+    if args.script.is_some() && std::env::var_os("SDL_VIDEODRIVER").is_none() {
+        //no window to show, screenshots are read back from the software renderer
+        std::env::set_var("SDL_VIDEODRIVER", "dummy");
+        std::env::set_var("SDL_RENDER_DRIVER", "software");
+    }
+
+    let sdl_context = sdl2::init().map_err(Error::Sdl)?;
+    let video_subsystem = sdl_context.video().map_err(Error::Sdl)?;
     let window = video_subsystem
-        .window("Mandelbrot Set Orbit Browser", 800, 600)
+        .window(
+            "Mandelbrot Set Orbit Browser",
+            initial_width,
+            initial_height,
+        )
         .position_centered()
         .resizable()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let mut canvas = window
-        .into_canvas()
-        .accelerated()
-        .build()
-        .map_err(|e| e.to_string())?;
-    println!("renderer info: {:?}", canvas.info());
-    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+        .build()?;
+    let mut canvas = window.into_canvas().accelerated().build()?;
+    debug!(target: "render", "renderer info: {:?}", canvas.info());
+    let ttf_context = sdl2::ttf::init()?;
     //desktop_display_mode
     //current_display_mode
     /*
@@ -119,324 +103,231 @@ fn main() -> Result<(), String> {
         println!("dm:{} x:{}, y:{}",i,dm.w,dm.h);
     }*/
     let creator = canvas.texture_creator();
-    let (initial_width, initial_height) = (800, 600);
+    video_subsystem.text_input().stop(); //until the console is opened
 
-    let j = Complex { re: 0.0, im: 1.0 };
-    let initial_view = ComplexBBox {
-        ll: -1.5 - j,
-        ur: 0.5 + j,
-    };
-    let mut view = initial_view;
-    let mut iterations = INITIAL_ITERATIONS;
-
-    let initial_bg_rect = Rect::new(0, 0, initial_width, initial_height);
-    let mut bg_rect_dest = initial_bg_rect.clone();
-    let mut bg_rect_src = initial_bg_rect.clone();
-
-    let mut bg_texture = creator
-        .create_texture_streaming(PixelFormatEnum::ARGB8888, initial_width, initial_height)
-        .map_err(|e| e.to_string())
-        .unwrap();
-    update_bg(&mut bg_texture, &view, iterations);
-
-    let mut drag_x: i32 = 0_i32;
-    let mut drag_y: i32 = 0;
-
-    let font_path = Path::new("assets/DejaVuSansMono.ttf");
-    let font = ttf_context.load_font(font_path, 12)?;
-
-    let red = Color::RGBA(255, 0, 0, 255);
-    let green = Color::RGBA(0, 255, 0, 255);
-    let _blue = Color::RGBA(0, 0, 255, 255);
-    let cyan = Color::RGBA(0, 255, 255, 255);
-    let magenta = Color::RGBA(255, 0, 255, 255);
-    let white = Color::RGBA(255, 255, 255, 255);
-
-    let mut show_coords_q = true;
-    let mut touch_zoom_in_progress = false;
-    let mut touch_zoom_pos = Point::new(0, 0);
-
-    let menu = menu::Menu::init(&creator, &ttf_context);
-    let mut display_menu_q = false;
+    let initial_view = args
+        .initial_view()
+        .or(config.view)
+        .unwrap_or_else(|| cli::centered(None, None));
+    let initial_iterations = args
+        .iterations
+        .or(config.iterations)
+        .unwrap_or(INITIAL_ITERATIONS);
+    let mut app = App::new(
+        initial_view,
+        initial_iterations,
+        (initial_width, initial_height),
+    );
+    app.saved_orbits = args.orbits.clone();
+    if let Some(path) = &args.open {
+        //rendered below, like any other starting view
+        app.open(path).map_err(Error::Open)?;
+    }
+    app.zoom_frames = args.zoom_frames.unwrap_or(app.zoom_frames);
+    app.zoom_depth = args.zoom_depth.unwrap_or(app.zoom_depth);
+
+    let mut bg_texture = creator.create_texture_streaming(
+        PixelFormatEnum::ARGB8888,
+        initial_width,
+        initial_height,
+    )?;
+    let mut perf = Perf::default();
+    let mut refine_pending = update_bg(&mut bg_texture, &app.view, &mut app.settings, &mut perf);
+
+    let font = font::load(&ttf_context, args.font.as_deref(), config.font_size)?;
+
+    let colors = config.colors;
+
+    let mut screenshot_request: Option<bool> = None; //Some(include the HUD?)
+
+    let menu = menu::Menu::init(
+        &creator,
+        &ttf_context,
+        args.font.as_deref(),
+        &colors,
+        &config.keys,
+    )?;
     let mut highlighted = None;
 
-    let mut pump = sdl_context.event_pump().unwrap();
-    let mut position = Complex { re: 0.0, im: 0.0 };
-    let mut saved_orbits: Vec<Complex<f64>> = Vec::new();
-    let mut full_screen = false;
+    let mut pump = sdl_context.event_pump().map_err(Error::Sdl)?;
+    if args.fullscreen {
+        app.full_screen = true;
+        canvas
+            .window_mut()
+            .set_fullscreen(sdl2::video::FullscreenType::Desktop)
+            .map_err(Error::Sdl)?;
+    }
+
+    //commands from the control socket and scripts, and the event that says
+    //they've come
+    let events = sdl_context.event().map_err(Error::Sdl)?;
+    let wake_type = unsafe { events.register_event().map_err(Error::Sdl)? };
+    let sender = events.event_sender();
+    let wake: control::Wake = Arc::new(move || {
+        let wake = Event::User {
+            timestamp: 0,
+            window_id: 0,
+            type_: wake_type,
+            code: 0,
+            data1: std::ptr::null_mut(),
+            data2: std::ptr::null_mut(),
+        };
+        if let Err(e) = sender.push_event(wake) {
+            warn!(target: "control", "Couldn't wake the main loop: {}", e);
+        }
+    });
+    let (request_sender, requests) = mpsc::channel::<Request>();
+    //anti-aliased backgrounds from the worker, numbered by the render they
+    //refine so those the view has moved on from can be dropped
+    let (refined_sender, refined) = mpsc::channel::<Refined>();
+    let mut bg_generation: u64 = 0;
+    let refine_wake = wake.clone();
+    if let Some(addr) = args.listen {
+        let addr = control::listen(addr, request_sender.clone(), wake.clone())?;
+        info!("Listening for commands on {}", addr);
+    }
+    if args.stdin {
+        control::read_stdin(request_sender.clone(), wake.clone());
+    }
+    let workers = control::Link {
+        requests: request_sender.clone(),
+        wake: wake.clone(),
+    };
+    let scripts = Scripts::start(request_sender, wake);
+    if let Some(path) = &args.script {
+        scripts.run(std::fs::read_to_string(path)?);
+        app.running_scripts += 1;
+    }
+    let mut script_result = None; //of --script, which ends the run
+    let mut screenshot_replies: Vec<control::Reply> = Vec::new();
+
+    let mut recorder = args.record.as_deref().map(Recorder::create).transpose()?;
+    let mut replay = args.replay.as_deref().map(Replay::load).transpose()?;
+    //the orbit follows the mouse position from the events, not SDL's idea of
+    //it, so that replays draw and save the same orbits
+    if replay.is_none() {
+        let state = pump.mouse_state();
+        app.mouse = geometry::Point::new(state.x(), state.y());
+    }
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.write(Recorded::MouseMotion {
+            x: app.mouse.x(),
+            y: app.mouse.y(),
+            xrel: 0,
+            yrel: 0,
+            left: false,
+            touch: false,
+        })?;
+    }
 
     'mainloop: loop {
-        let mut potential_event = Some(pump.wait_event()); //Blocking call will always succeed
+        let next_step = app
+            .playback
+            .as_ref()
+            .and_then(|p| p.time_to_next_step(Instant::now()));
+        let wait = if refine_pending {
+            Some(Duration::ZERO)
+        } else {
+            next_step //wake up in time to show the next iterate of the orbit playback
+        };
+        let mut potential_event = next_event(&mut pump, &mut canvas, &mut replay, wait)?;
+        if potential_event.is_none() && refine_pending {
+            //nothing else to do, anti-alias the fast preview off this thread
+            refine_bg(
+                &bg_texture,
+                &app.view,
+                &app.settings,
+                bg_generation,
+                refined_sender.clone(),
+                refine_wake.clone(),
+            );
+            refine_pending = false;
+        }
 
         while let Some(event) = potential_event {
-            let win_size = canvas.viewport().size();
-            let win_width: i32 = win_size.0.try_into().unwrap();
-            let win_height: i32 = win_size.1.try_into().unwrap();
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&event)?;
+            }
+            if let Event::MouseMotion { x, y, .. }
+            | Event::MouseButtonDown { x, y, .. }
+            | Event::MouseButtonUp { x, y, .. } = event
+            {
+                app.mouse = geometry::Point::new(x, y);
+            }
 
-            match event {
-                Event::KeyDown {
-                    keycode: Some(Keycode::Q),
-                    ..
-                }
-                | Event::Quit { .. } => break 'mainloop,
+            //keys, mouse buttons, menu items and the wheel all become actions
+            let action = match event {
                 Event::KeyDown {
-                    keycode: Some(Keycode::C),
+                    keycode: Some(key),
+                    keymod,
                     ..
-                } => {
-                    show_coords_q = !show_coords_q;
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::I),
-                    ..
-                } => {
-                    iterations *= 2;
-                    update_bg(&mut bg_texture, &view, iterations);
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::F),
-                    ..
-                } => {
-                    //investigate "full screen" in browser, seems to be less than full resolution
-                    //suspicously 20% lower: (1138 x 640) instead of (1366 x 768)
-                    println!("full_screen:{}, event:{:?}", full_screen, event);
-                    canvas.window_mut().set_fullscreen(if full_screen {
-                        sdl2::video::FullscreenType::Off
-                    } else {
-                        sdl2::video::FullscreenType::Desktop
-                    })?;
-                    full_screen = !full_screen;
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => {
-                    if full_screen {
-                        canvas
-                            .window_mut()
-                            .set_fullscreen(sdl2::video::FullscreenType::Off)?;
-                        full_screen = false;
+                } => match config.keys.action(Input::key(key, keymod)) {
+                    //the console takes the other keys while it's open, and
+                    //this one too once something's typed (Rhai strings can be
+                    //in backquotes), the command line takes all of them
+                    Some(Action::ToggleConsole)
+                        if app.command_line.is_none()
+                            && (!app.console.open || app.console.input.is_empty()) =>
+                    {
+                        Some(Action::ToggleConsole)
                     }
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Home),
-                    ..
-                } => {
-                    view = initial_view;
-                    iterations = INITIAL_ITERATIONS;
-                    bg_rect_src = Rect::new(0, 0, win_size.0, win_size.1);
-                    bg_rect_dest = Rect::new(0, 0, win_size.0, win_size.1);
-                    update_bg(&mut bg_texture, &view, iterations);
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Right),
-                    repeat: _,
-                    ..
-                }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Kp6),
-                    repeat: _,
-                    ..
-                } => {
-                    let mouse_state = pump.mouse_state();
-                    let (mx, my) = (mouse_state.x(), mouse_state.y());
-                    println!("Right, {:?},{:?}", (mx, my), event);
-                    sdl_context
-                        .mouse()
-                        .warp_mouse_in_window(canvas.window(), mx + 1, my);
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Left),
-                    repeat: _,
-                    ..
-                }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Kp4),
-                    repeat: _,
-                    ..
-                } => {
-                    let mouse_state = pump.mouse_state();
-                    let (mx, my) = (mouse_state.x(), mouse_state.y());
-                    sdl_context
-                        .mouse()
-                        .warp_mouse_in_window(canvas.window(), mx - 1, my);
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Up),
-                    repeat: _,
-                    ..
-                }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Kp8),
-                    repeat: _,
-                    ..
-                } => {
-                    let mouse_state = pump.mouse_state();
-                    let (mx, my) = (mouse_state.x(), mouse_state.y());
-                    sdl_context
-                        .mouse()
-                        .warp_mouse_in_window(canvas.window(), mx, my - 1);
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Down),
-                    repeat: _,
-                    ..
-                }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Kp2),
-                    repeat: _,
-                    ..
-                } => {
-                    let mouse_state = pump.mouse_state();
-                    let (mx, my) = (mouse_state.x(), mouse_state.y());
-                    sdl_context
-                        .mouse()
-                        .warp_mouse_in_window(canvas.window(), mx, my + 1);
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Comma),
-                    repeat: _,
-                    ..
-                } => {
-                    /* decrease the radius */
-                    // need to do line drawing algorithm to increase radius by one-pixel
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Period),
-                    repeat: _,
-                    ..
-                } => { /* increase the radius */ }
-                Event::KeyDown {
-                    keycode: Some(Keycode::RightBracket),
-                    repeat: _,
-                    ..
-                } => {
-                    /* rotate cloclwise */
-                    // need to do circle drawing algorithm to increase along the circle by one pixel
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::LeftBracket),
-                    repeat: _,
-                    ..
-                } => { /* rotate counter-clockwise */ }
-                Event::KeyDown {
-                    keycode: Some(Keycode::M),
+                    _ if app.typing() => None,
+                    action => action,
+                },
+                Event::MouseButtonDown {
+                    which,
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
                     ..
-                } => {
-                    display_menu_q = !display_menu_q;
+                } if which != SDL_TOUCH_MOUSEID && app.display_menu_q => {
+                    let picked = menu.selected(x, y).map(|(action, _, _)| *action);
+                    debug!(target: "menu", "picked {:?}", picked);
+                    picked
                 }
+                Event::MouseButtonDown {
+                    which, mouse_btn, ..
+                } if which != SDL_TOUCH_MOUSEID => config.keys.action(Input::Mouse(mouse_btn)),
+                Event::MouseWheel { y, .. } => Some(if y > 0 {
+                    Action::ZoomIn
+                } else {
+                    Action::ZoomOut
+                }),
+                _ => None,
+            };
+
+            let effects = match event {
+                Event::Quit { .. } => break 'mainloop,
                 Event::MouseButtonUp {
                     which, mouse_btn, ..
-                } if which != SDL_TOUCH_MOUSEID => {
-                    //recalculate new view bounding box
-                    if mouse_btn == MouseButton::Left {
-                        {
-                            //finishing up dragging/panning
-                            let shift = view.complex_deltas(win_width, win_height, drag_x, drag_y);
-                            view = ComplexBBox {
-                                ll: view.ll - shift,
-                                ur: view.ur - shift,
-                            };
-                            bg_rect_dest = Rect::new(0, 0, win_size.0, win_size.1); //reset bg_rect
-                            update_bg(&mut bg_texture, &view, iterations);
-                            let _state = pump.relative_mouse_state(); //reset relative coordinates
-                            drag_x = 0;
-                            drag_y = 0;
-                        }
-                    }
-                    display_menu_q = false;
-                }
-                //Event::MouseButtonDown{which, mouse_btn:MouseButton::Right, .. } if which != SDL_TOUCH_MOUSEID |
-                Event::KeyDown {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => {
-                    //TODO: Consolidate with MouseButtonDown -> MouseButton::Right below
-                    let mouse_state = pump.mouse_state();
-                    let (mx, my) = (mouse_state.x(), mouse_state.y());
-                    let c = view.screen_to_complex(mx, my, win_width, win_height);
-                    saved_orbits = calc_orbits(c);
-                }
+                } if which != SDL_TOUCH_MOUSEID => app.release(mouse_button(mouse_btn)),
                 Event::MouseButtonDown {
                     which,
-                    mouse_btn,
-                    x,
-                    y,
-                    window_id,
-                    timestamp,
-                    clicks,
+                    mouse_btn: MouseButton::Left,
                     ..
                 } if which != SDL_TOUCH_MOUSEID => {
-                    match mouse_btn {
-                        MouseButton::Left => {
-                            if !display_menu_q {
-                                let _state = pump.relative_mouse_state(); //reset relative coordinates in SDL land
-                                drag_x = 0;
-                                drag_y = 0;
-                            } else {
-                                //Was a menu item selected?
-                                if let Some((action, _, _)) = menu.selected(x, y) {
-                                    if action.clone() == Some(Keycode::F) {
-                                        canvas.window_mut().set_fullscreen(if full_screen {
-                                            sdl2::video::FullscreenType::Off
-                                        } else {
-                                            sdl2::video::FullscreenType::Desktop
-                                        })?;
-                                        full_screen = !full_screen;
-                                        let simulated_mouse_up: Event = Event::MouseButtonUp {
-                                            which: which,
-                                            mouse_btn: mouse_btn,
-                                            x: x,
-                                            y: y,
-                                            clicks: clicks,
-                                            timestamp: timestamp + 1,
-                                            window_id: window_id,
-                                        };
-
-                                        let event = sdl_context.event().unwrap();
-                                        event.push_event(simulated_mouse_up)?;
-                                    } else {
-                                        let simulated_keydown: Event = Event::KeyDown {
-                                            keycode: action.clone(),
-                                            timestamp: timestamp + 1,
-                                            scancode: Some(sdl2::keyboard::Scancode::F),
-                                            window_id: window_id,
-                                            keymod: sdl2::keyboard::Mod::NOMOD,
-                                            repeat: false,
-                                        };
-                                        let event = sdl_context.event().unwrap();
-                                        event.push_event(simulated_keydown)?;
-                                    } //if action.clone()
-                                } // if let
-                            } // if !diaplay_menu_q
-                            display_menu_q = false;
-                        } //MouseButton::Left
-                        MouseButton::Right => {
-                            let mouse_state = pump.mouse_state();
-                            let (mx, my) = (mouse_state.x(), mouse_state.y());
-                            let c = view.screen_to_complex(mx, my, win_width, win_height);
-                            saved_orbits = calc_orbits(c);
-                        }
-                        _ => {
-                            println!("unhandeled mouse button");
-                        }
-                    }
+                    //menu items were picked above
+                    app.press()
                 }
-                Event::MouseMotion { x, y, which, .. } if which != SDL_TOUCH_MOUSEID => {
-                    //if pump.mouse_state().is_mouse_button_pressed(MouseButton::Left) {
-                    //if MouseState::new(pump).left() {
-                    if pump.mouse_state().left() {
-                        //panning
-                        //TODO: Problem with emscripten thinking that left mouse button is pressed after return from full screen mode
-                        println!("left pressed...");
-                        let state = pump.relative_mouse_state();
-                        drag_x += state.x();
-                        drag_y += state.y();
-                        bg_rect_dest.set_x(bg_rect_dest.x() + state.x());
-                        bg_rect_dest.set_y(bg_rect_dest.y() + state.y());
-                    } else {
-                        position = view.screen_to_complex(x, y, win_width, win_height);
+                Event::DropFile { ref filename, .. } => match app.open(Path::new(filename)) {
+                    Ok(effects) => effects,
+                    Err(e) => {
+                        warn!("Couldn't open {}", e);
+                        Effects::default()
                     }
+                },
+                Event::MouseMotion {
+                    x,
+                    y,
+                    xrel,
+                    yrel,
+                    which,
+                    mousestate,
+                    ..
+                } if which != SDL_TOUCH_MOUSEID => {
                     highlighted = menu.selected(x, y);
+                    //buttons and motion as of this event, so replays pan the same
+                    app.motion(x, y, xrel, yrel, mousestate.left())
                 }
                 Event::FingerDown { x, y, .. } | Event::FingerMotion { x, y, .. } => {
                     let _ignore = (x, y);
@@ -445,221 +336,606 @@ fn main() -> Result<(), String> {
                     if !touch_zoom_in_progress {
                         //orbit_points = calc_orbits(x,y,w1.try_into().unwrap(),h1.try_into().unwrap(),&view);
                     }*/
-                    {}
-                }
-                Event::FingerUp { x: _, y: _, .. } => {
-                    if touch_zoom_in_progress {
-                        touch_zoom_in_progress = false;
-                        let (zx, zy) = (touch_zoom_pos.x(), touch_zoom_pos.y());
-                        let complex_pos = view.screen_to_complex(zx, zy, win_width, win_height);
-                        let zoomies = (bg_rect_src.width() as f64) / (bg_rect_dest.width() as f64); //if y>0 {0.5} else {2.0};
-                        view = view.zoom(complex_pos, zoomies);
-                        bg_rect_dest = Rect::new(0, 0, win_size.0, win_size.1);
-                        bg_rect_src = Rect::new(0, 0, win_size.0, win_size.1);
-                        update_bg(&mut bg_texture, &view, iterations);
-                    }
-                    {}
+                    Effects::default()
                 }
+                Event::FingerUp { .. } => app.finger_up(),
                 Event::MultiGesture {
                     x,
                     y,
                     d_dist,
                     num_fingers,
                     ..
-                } => {
-                    if num_fingers == 2 {
-                        touch_zoom_in_progress = true;
-                        let x = (x * win_width as f32).floor() as i32;
-                        let y = (y * win_height as f32).floor() as i32;
-                        touch_zoom_pos = Point::new(x, y);
-                        //println!("Touch Zoom {}: {:.4} @ ({},{})",if d_dist>0.0 {"in "} else {"out"},d_dist,x,y);
-
-                        //rescale image until FingerUp, then recalculate bg_texture, and reset
-                        // bg_rect_src, bg_rect_dest, etc.
-                        /*Copies a portion of the texture to the current rendering target.
-                        If src is None, the entire texture is copied.
-                        If dst is None, the texture will be stretched to fill the given rectangle.*/
-                        //todo also do panning with two fingers
-                        //fix to only adjust one rect if the other hasn't yet been modified.  i.e. pinch zoom isn't finished yet
-                        if d_dist > 0.0 {
-                            //zoom in
-                            let new_width =
-                                ((bg_rect_src.width() as f32) * (1.0 - 10.0 * d_dist)) as u32;
-                            let new_height =
-                                ((bg_rect_src.height() as f32) * (1.0 - 10.0 * d_dist)) as u32;
-                            bg_rect_src.set_width(new_width);
-                            bg_rect_src.set_height(new_height);
-                            bg_rect_src.center_on(Point::new(x, y));
-                            bg_rect_dest = Rect::new(0, 0, win_size.0, win_size.1);
-                        //initial_bg_rect.clone(); //maybe don't reset dest?
-                        } else {
-                            //zoom out
-                            bg_rect_src = Rect::new(0, 0, win_size.0, win_size.1); //initial_bg_rect.clone(); //maybe don't reset src?
-                                                                                   //remember d_dist is negative here
-                            let new_width =
-                                ((bg_rect_dest.width() as f32) * (1.0 + 10.0 * d_dist)) as u32;
-                            let new_height =
-                                ((bg_rect_dest.height() as f32) * (1.0 + 10.0 * d_dist)) as u32;
-                            bg_rect_dest.set_width(new_width);
-                            bg_rect_dest.set_height(new_height);
-                            bg_rect_dest.center_on(Point::new(x, y));
-                        }
-                    }
-                    //num_fingers == 2
-                    else {
-                        println!("Multi-touch num_fingers: {}", num_fingers);
-                    }
-                } //Event::MultiGesture
-                Event::MouseWheel { y, .. } => {
-                    let mouse_state = pump.mouse_state();
-                    let (mx, my) = (mouse_state.x(), mouse_state.y());
-                    //let (w1,h1) = canvas.viewport().size();
-                    let complex_pos = view.screen_to_complex(mx, my, win_width, win_height);
-                    //println!("Zoom {} @ {:?}",if y>0 {"in"} else {"out"},(mx,my));
-                    let zoomies = if y > 0 { 0.5 } else { 2.0 };
-                    view = view.zoom(complex_pos, zoomies);
-                    update_bg(&mut bg_texture, &view, iterations);
-                }
+                } => app.pinch(x, y, d_dist, num_fingers),
                 Event::Window {
                     win_event: WindowEvent::SizeChanged(x, y),
                     ..
                 } => {
-                    println!("Got Size change -- x:{}, y:{}", x, y);
-                    println!("size change event {:?}", event);
-                    let new_size = canvas.viewport().size();
-                    let nx = new_size.0;
-                    let ny = new_size.1;
-                    bg_rect_src = Rect::new(0, 0, nx, ny);
-                    bg_rect_dest = Rect::new(0, 0, nx, ny);
-                    let before = Instant::now();
+                    debug!(target: "events", "size change event {:?}", event);
+                    let (nx, ny) = canvas.viewport().size();
+                    let effects = app.resized((nx, ny));
                     //NEED NEW TEXTURE HERE, CAN'T JUST UPDATE!!
-                    bg_texture = creator
-                        .create_texture_streaming(PixelFormatEnum::ARGB8888, nx, ny)
-                        .map_err(|e| e.to_string())
-                        .unwrap();
-                    update_bg(&mut bg_texture, &view, iterations);
-                    let after = before.elapsed();
-                    println!("Resize time: {:?}", after);
+                    match creator.create_texture_streaming(PixelFormatEnum::ARGB8888, nx, ny) {
+                        Ok(texture) => bg_texture = texture,
+                        Err(e) => {
+                            //keep going with the old background stretched over the window
+                            warn!(target: "render", "Couldn't resize the background: {}", e);
+                            let TextureQuery { width, height, .. } = bg_texture.query();
+                            app.bg_rect_src = geometry::Rect::new(0, 0, width, height);
+                        }
+                    }
+                    debug!(target: "render", "Resize to {}x{}", x, y);
+                    effects
                 }
-                Event::KeyUp { keycode, .. } if keycode != Some(Keycode::M) => {
-                    println!("keyup: {:?}", event);
-                    display_menu_q = false;
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } if app.typing() && action.is_none() => edit_key(key)
+                    .map(|key| app.typing_key(key))
+                    .unwrap_or_default(),
+                Event::TextInput { ref text, .. } => {
+                    app.text_input(text);
+                    Effects::default()
+                }
+                Event::User { type_, .. } if type_ == wake_type => {
+                    let mut effects = Effects::default();
+                    for Request { command, reply } in requests.try_iter() {
+                        let screenshot = matches!(command, Command::Screenshot { .. });
+                        if let Command::ScriptDone(result) = &command {
+                            if args.script.is_some() && script_result.is_none() {
+                                script_result = Some(result.clone());
+                                effects.quit = true;
+                            }
+                        }
+                        let (result, mut more) = control::execute(&mut app, command);
+                        //each request's task, several can come at once
+                        if let Some(task) = more.task.take() {
+                            run_task(
+                                task,
+                                &app,
+                                &colors,
+                                &scripts,
+                                &workers,
+                                &mut screenshot_request,
+                            );
+                        }
+                        if screenshot {
+                            screenshot_replies.push(reply); //once it's taken
+                        } else {
+                            let _ = reply.send(Ok(result));
+                        }
+                        effects = effects.and(more);
+                    }
+                    effects
                 }
+                Event::KeyUp {
+                    keycode, keymod, ..
+                } => {
+                    trace!(target: "events", "keyup: {:?}", event);
+                    app.key_up(keycode.and_then(|key| config.keys.action(Input::key(key, keymod))))
+                }
+                _ if action.is_some() => Effects::default(),
                 _ => {
-                    println!("unhandeled event: {:?}", event);
+                    trace!(target: "events", "unhandeled event: {:?}", event);
+                    Effects::default()
                 }
-            } //match event
-            potential_event = pump.poll_event();
+            }; //match event
+
+            let effects = match action {
+                Some(action) => effects.and(app.handle(action)),
+                None => effects,
+            };
+            if effects.quit {
+                break 'mainloop;
+            }
+            if let Some(full_screen) = effects.fullscreen {
+                //investigate "full screen" in browser, seems to be less than full resolution
+                //suspicously 20% lower: (1138 x 640) instead of (1366 x 768)
+                canvas
+                    .window_mut()
+                    .set_fullscreen(if full_screen {
+                        sdl2::video::FullscreenType::Desktop
+                    } else {
+                        sdl2::video::FullscreenType::Off
+                    })
+                    .map_err(Error::Sdl)?;
+            }
+            if let Some(to) = effects.warp_mouse {
+                sdl_context
+                    .mouse()
+                    .warp_mouse_in_window(canvas.window(), to.x(), to.y());
+            }
+            if let Some(text_input) = effects.text_input {
+                if text_input {
+                    video_subsystem.text_input().start();
+                } else {
+                    video_subsystem.text_input().stop();
+                }
+            }
+            if let Some(task) = effects.task {
+                run_task(
+                    task,
+                    &app,
+                    &colors,
+                    &scripts,
+                    &workers,
+                    &mut screenshot_request,
+                );
+            }
+            if effects.render {
+                refine_pending =
+                    update_bg(&mut bg_texture, &app.view, &mut app.settings, &mut perf);
+                bg_generation += 1;
+            }
+            potential_event =
+                next_event(&mut pump, &mut canvas, &mut replay, Some(Duration::ZERO))?;
         } //while events
 
-        canvas.set_draw_color(white);
+        for r in refined.try_iter() {
+            show_refined(&mut bg_texture, r, bg_generation);
+        }
+
+        canvas.set_draw_color(colors.background);
         canvas.clear();
-        canvas.copy(&bg_texture, bg_rect_src, bg_rect_dest).unwrap();
+        canvas
+            .copy(
+                &bg_texture,
+                sdl_rect(app.bg_rect_src),
+                sdl_rect(app.bg_rect_dest),
+            )
+            .map_err(Error::Sdl)?;
 
         //draw orbits for current position
+        let overlay_start = Instant::now();
         {
             let (w1, h1) = canvas.viewport().size();
             let (w, h) = (w1.try_into().unwrap(), h1.try_into().unwrap());
-            let mouse_state = pump.mouse_state();
-            let (mx, my) = (mouse_state.x(), mouse_state.y());
-            let c = view.screen_to_complex(mx, my, w, h);
-            let orbit_points = calc_orbits(c);
-            let current_points = orbit_points
-                .iter()
-                .map(|x| view.complex_to_screen(*x, w, h));
-            draw_orbits(&mut canvas, &current_points.collect(), red, green)?;
-            if saved_orbits.len() > 0 {
-                let saved_points = saved_orbits
+            if let Some(p) = app.playback.as_mut() {
+                //the played orbit takes the place of the one under the cursor
+                let orbit_points = calc_orbits(p.seed, &app.bailout);
+                p.advance(orbit_points.len(), Instant::now());
+                let shown_points = orbit_points
                     .iter()
-                    .map(|x| view.complex_to_screen(*x, w, h));
-                draw_orbits(&mut canvas, &saved_points.collect(), magenta, cyan)?;
+                    .take(p.index + 1)
+                    .map(|x| sdl_point(app.view.complex_to_screen(*x, w, h)));
+                orbit_draw::draw_orbit_trail(
+                    &mut canvas,
+                    &creator,
+                    &font,
+                    &shown_points.collect::<Vec<Point>>(),
+                    colors.orbit_first,
+                    colors.orbit,
+                    &app.orbit_style,
+                    playback::TRAIL,
+                )
+                .map_err(Error::Sdl)?;
+            } else {
+                let c = app
+                    .view
+                    .screen_to_complex(app.mouse.x(), app.mouse.y(), w, h);
+                let orbit_points = calc_orbits(c, &app.bailout);
+                let current_points = orbit_points
+                    .iter()
+                    .map(|x| sdl_point(app.view.complex_to_screen(*x, w, h)));
+                orbit_draw::draw_orbits(
+                    &mut canvas,
+                    &creator,
+                    &font,
+                    &current_points.collect::<Vec<Point>>(),
+                    colors.orbit_first,
+                    colors.orbit,
+                    &app.orbit_style,
+                )
+                .map_err(Error::Sdl)?;
             }
+            for saved in app.saved_orbits.iter() {
+                let saved_points = calc_orbits(*saved, &app.bailout)
+                    .into_iter()
+                    .map(|x| sdl_point(app.view.complex_to_screen(x, w, h)));
+                orbit_draw::draw_orbits(
+                    &mut canvas,
+                    &creator,
+                    &font,
+                    &saved_points.collect::<Vec<Point>>(),
+                    colors.saved_first,
+                    colors.saved,
+                    &app.orbit_style,
+                )
+                .map_err(Error::Sdl)?;
+            }
+        }
+
+        perf.overlay = overlay_start.elapsed();
+
+        if screenshot_request == Some(false) {
+            save_screenshot(&canvas, &app.metadata(), &mut screenshot_replies);
         }
 
-        if show_coords_q {
-            let tmp = format!("{:.8} {:+.8}i", position.re, position.im);
+        let mut hud_lines = Vec::new();
+        if app.show_perf_q {
+            hud_lines.extend(perf.hud_lines(&app.settings, rayon::current_num_threads()));
+        }
+        if let Some(p) = &app.playback {
+            let orbit_points = calc_orbits(p.seed, &app.bailout);
+            if let Some(z) = orbit_points.get(p.index) {
+                hud_lines.push(format!(
+                    "iterate {}/{}  z = {:.8} {:+.8}i  {}/s{}",
+                    p.index,
+                    orbit_points.len() - 1,
+                    z.re,
+                    z.im,
+                    p.speed,
+                    if p.paused { "  (paused)" } else { "" }
+                ));
+            }
+        }
+        if app.show_coords_q {
+            hud_lines.push(format!(
+                "{:.8} {:+.8}i  iterations: {}{}",
+                app.position.re,
+                app.position.im,
+                app.settings.iterations,
+                if app.settings.auto_iterations {
+                    " (auto)"
+                } else {
+                    ""
+                }
+            ));
+        }
+        if let Some(status) = &app.status {
+            hud_lines.push(status.clone());
+        }
+        if let Some(message) = &app.message {
+            hud_lines.push(message.clone());
+        }
+        if let Some(line) = &app.command_line {
+            hud_lines.push(format!(":{}_", line));
+        }
+        //stacked up from the bottom left corner, last line lowest
+        let mut text_bottom = canvas.viewport().height() as i32 - 5;
+        for line in hud_lines.iter().rev() {
             let coord_disp_surf = font
-                .render(tmp.as_str())
-                .shaded(
-                    Color::RGBA(125, 0, 125, 255),
-                    Color::RGBA(200, 200, 200, 255),
-                )
-                .map_err(|e| e.to_string())?;
-            let coord_disp_texture = creator
-                .create_texture_from_surface(&coord_disp_surf)
-                .map_err(|e| e.to_string())?;
+                .render(line.as_str())
+                .shaded(colors.coordinates_text, colors.coordinates_background)?;
+            let coord_disp_texture = creator.create_texture_from_surface(&coord_disp_surf)?;
             let TextureQuery { width, height, .. } = coord_disp_texture.query();
-            let text_rect = Rect::new(
-                5,
-                (canvas.viewport().size().1 - height - 5)
-                    .try_into()
-                    .unwrap(),
-                width,
-                height,
-            );
-            canvas.copy(&coord_disp_texture, None, text_rect)?;
+            text_bottom -= height as i32;
+            let text_rect = Rect::new(5, text_bottom, width, height);
+            canvas
+                .copy(&coord_disp_texture, None, text_rect)
+                .map_err(Error::Sdl)?;
+            text_bottom -= 2;
+        }
+
+        if screenshot_request.take() == Some(true) {
+            save_screenshot(&canvas, &app.metadata(), &mut screenshot_replies);
+        }
+
+        if app.console.open {
+            draw_console(&mut canvas, &creator, &font, &app, &colors)?;
         }
 
-        if display_menu_q {
-            canvas.copy(&menu.texture, None, menu.offset_rect).unwrap();
+        if app.display_menu_q {
+            canvas
+                .copy(&menu.texture, None, menu.offset_rect)
+                .map_err(Error::Sdl)?;
             if let Some((_action, hi_rect, hi_text)) = highlighted {
                 //println!("Hover: {}",name);
                 let hi_dest = hi_rect.clone();
                 //let (w,h) = (hi_rect.width(), hi_rect.height());
-                canvas.copy(&hi_text, None, hi_dest).unwrap();
+                canvas.copy(&hi_text, None, hi_dest).map_err(Error::Sdl)?;
             }
         }
 
         canvas.present();
     } //mainloop
 
-    println!("Exiting...");
+    if let Some(path) = &args.dump_state {
+        std::fs::write(path, app.state().to_string())?;
+    }
+    if let Some(Err(e)) = script_result {
+        return Err(Error::Script(e));
+    }
+    debug!("Exiting...");
     Ok(())
 }
 
-fn calc_orbits(c: Complex<f64>) -> Vec<Complex<f64>> {
-    let iter = 50;
-    let limit_sqr = 2.0 * 2.0;
-    let mut z = Complex { re: 0.0, im: 0.0 };
-    let mut points = Vec::new();
+//The next event, from the replay while it lasts.  Waits up to `wait` for one,
+//or for as long as it takes if None.
+fn next_event(
+    pump: &mut EventPump,
+    canvas: &mut Canvas<Window>,
+    replay: &mut Option<Replay>,
+    wait: Option<Duration>,
+) -> Result<Option<Event>, Error> {
+    if let Some(r) = replay {
+        if !r.finished() {
+            return r.next(pump, canvas, wait);
+        }
+        info!("Replay finished");
+        *replay = None;
+    }
+    Ok(match wait {
+        Some(wait) if wait.is_zero() => pump.poll_event(),
+        Some(wait) => pump.wait_event_timeout(wait.as_millis().try_into().unwrap_or(u32::MAX)),
+        None => Some(pump.wait_event()), //Blocking call will always succeed
+    })
+}
 
-    points.push(z); //origin
-    points.push(c); //first point/mouse cursor position
+//App keeps to its own types for input and geometry, see app.rs
+fn mouse_button(button: MouseButton) -> app::MouseButton {
+    match button {
+        MouseButton::Left => app::MouseButton::Left,
+        _ => app::MouseButton::Other,
+    }
+}
 
-    for _i in 0..iter {
-        let z_next = z * z + c;
-        if z_next.norm_sqr() > limit_sqr {
-            break;
-        }
-        points.push(z_next);
-        z = z_next;
+fn edit_key(key: Keycode) -> Option<EditKey> {
+    match key {
+        Keycode::Return | Keycode::KpEnter => Some(EditKey::Enter),
+        Keycode::Backspace => Some(EditKey::Backspace),
+        Keycode::Escape => Some(EditKey::Escape),
+        _ => None,
     }
+}
 
-    points
+fn sdl_point(p: geometry::Point) -> Point {
+    Point::new(p.x(), p.y())
 }
 
-fn draw_orbits(
-    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
-    ps: &Vec<Point>,
-    c1: Color,
-    c2: Color,
-) -> Result<(), String> {
-    let mut first_q = true;
-
-    for (p1, p2) in ps.iter().tuple_windows() {
-        if first_q {
-            first_q = false;
-            canvas.set_draw_color(c1);
-        } else {
-            canvas.set_draw_color(c2);
+fn sdl_rect(r: geometry::Rect) -> Rect {
+    Rect::new(r.x(), r.y(), r.width(), r.height())
+}
+
+//Carry out the work an action left for main
+fn run_task(
+    task: Task,
+    app: &App,
+    colors: &Colors,
+    scripts: &Scripts,
+    workers: &control::Link,
+    screenshot_request: &mut Option<bool>,
+) {
+    let view = app.view;
+    let (bailout, settings) = (app.bailout, app.settings);
+    match task {
+        Task::ExportOrbits { c, json } => {
+            let (contents, extension) = if json {
+                (
+                    export::orbits_json(Some(c), &app.saved_orbits, &bailout),
+                    "json",
+                )
+            } else {
+                (
+                    export::orbits_csv(Some(c), &app.saved_orbits, &bailout),
+                    "csv",
+                )
+            };
+            let path = export_path("orbits", extension);
+            match std::fs::write(&path, contents) {
+                Ok(()) => info!("Exported orbits to {}", path),
+                Err(e) => warn!("Couldn't write {}: {}", path, e),
+            }
+        }
+        Task::Screenshot { hud } => {
+            //taken while drawing the next frame
+            *screenshot_request = Some(hud);
         }
-        canvas.draw_line(*p1, *p2)?;
+        Task::ExportLocation => {
+            //the same location for Kalles Fraktaler, XaoS and Fractint
+            let here = location::Location {
+                view,
+                iterations: settings.iterations,
+            };
+            for format in location::Format::ALL {
+                let path = export_path("location", format.extension());
+                match std::fs::write(&path, location::write(format, &here)) {
+                    Ok(()) => info!("Exported location to {}", path),
+                    Err(e) => warn!("Couldn't write {}: {}", path, e),
+                }
+            }
+        }
+        Task::ZoomSequence {
+            c,
+            with_orbit,
+            frames,
+            depth,
+        } => {
+            let sequence = ZoomSequence {
+                start: view,
+                target: zoom_sequence::target(&view, c, depth),
+                frames,
+                width: app.size.0 as usize,
+                height: app.size.1 as usize,
+                settings,
+                base_iterations: INITIAL_ITERATIONS,
+                orbit: if with_orbit { Some((c, bailout)) } else { None },
+            };
+            let directory = export_path("zoom", "");
+            let link = workers.clone();
+            //the frames are written while the viewer carries on, with the
+            //progress shown in the HUD
+            let export = move || {
+                let result = sequence.export(Path::new(&directory), |done, total| {
+                    if done % 10 == 0 || done == total {
+                        info!("Zoom sequence: frame {} of {}", done, total);
+                    }
+                    let status = format!("zoom sequence: frame {} of {}", done, total);
+                    link.notify(Command::Status(Some(status)));
+                });
+                match result {
+                    Ok(paths) => info!("Wrote {} frames to {}", paths.len(), directory),
+                    Err(e) => warn!("Couldn't write zoom sequence to {}: {}", directory, e),
+                }
+                link.notify(Command::Status(None));
+            };
+            //emscripten target don't yet support multi-threading
+            #[cfg(target_os = "emscripten")]
+            export();
+            #[cfg(not(target_os = "emscripten"))]
+            std::thread::spawn(export);
+        }
+        Task::ExportSvg { c, axes } => {
+            let (w, h) = (app.size.0 as usize, app.size.1 as usize);
+            let mut export_settings = settings;
+            let background =
+                render::render_rgba(&view, w, h, &mut export_settings, INITIAL_ITERATIONS);
+            let current = calc_orbits(c, &bailout);
+            let saved: Vec<Vec<Complex<f64>>> = app
+                .saved_orbits
+                .iter()
+                .map(|s| calc_orbits(*s, &bailout))
+                .collect();
+            let (orbit_first, orbit, saved_first, saved_color) = (
+                css(colors.orbit_first),
+                css(colors.orbit),
+                css(colors.saved_first),
+                css(colors.saved),
+            );
+            let mut svg_orbits = vec![SvgOrbit {
+                points: &current,
+                first_color: &orbit_first,
+                color: &orbit,
+            }];
+            svg_orbits.extend(saved.iter().map(|points| SvgOrbit {
+                points,
+                first_color: &saved_first,
+                color: &saved_color,
+            }));
+            let options = SvgOptions {
+                axes,
+                label: if app.show_coords_q {
+                    Some(format!("{:.8} {:+.8}i", c.re, c.im))
+                } else {
+                    None
+                },
+            };
+            let path = export_path("view", "svg");
+            match svg::view_svg(&view, w, h, &background, &svg_orbits, &options)
+                .and_then(|doc| std::fs::write(&path, doc).map_err(|e| e.to_string()))
+            {
+                Ok(()) => info!("Exported view to {}", path),
+                Err(e) => warn!("Couldn't write {}: {}", path, e),
+            }
+        }
+        Task::About => {
+            for line in menu::about() {
+                info!(target: "menu", "{}", line);
+            }
+        }
+        Task::RunScript(source) => scripts.run(source),
+        Task::CancelScripts => scripts.cancel(),
     }
+}
 
+//The console across the top of the window, its output above the line being
+//typed
+fn draw_console(
+    canvas: &mut Canvas<Window>,
+    creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    app: &App,
+    colors: &Colors,
+) -> Result<(), Error> {
+    let console = &app.console;
+    let input = if app.running_scripts > 0 {
+        format!("> {}_    (running, Escape cancels)", console.input)
+    } else {
+        format!("> {}_", console.input)
+    };
+    let lines: Vec<&str> = console
+        .last_lines(CONSOLE_LINES)
+        .map(String::as_str)
+        .chain(std::iter::once(input.as_str()))
+        .collect();
+    let line_height = font.height() + 2;
+    let height = line_height * lines.len() as i32 + 6;
+    canvas.set_draw_color(colors.coordinates_background);
+    canvas
+        .fill_rect(Rect::new(0, 0, canvas.viewport().width(), height as u32))
+        .map_err(Error::Sdl)?;
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            continue; //SDL_ttf won't render nothing
+        }
+        let surface = font
+            .render(line)
+            .shaded(colors.coordinates_text, colors.coordinates_background)?;
+        let texture = creator.create_texture_from_surface(&surface)?;
+        let TextureQuery { width, height, .. } = texture.query();
+        canvas
+            .copy(
+                &texture,
+                None,
+                Rect::new(5, 3 + i as i32 * line_height, width, height),
+            )
+            .map_err(Error::Sdl)?;
+    }
     Ok(())
 }
 
-fn update_bg(bg_texture: &mut sdl2::render::Texture, view: &ComplexBBox, iter: u32) -> () {
+//Read back what has been drawn so far and write it to a PNG, telling the
+//control socket clients waiting for it where it went
+fn save_screenshot(
+    canvas: &sdl2::render::Canvas<sdl2::video::Window>,
+    metadata: &ViewMetadata,
+    replies: &mut Vec<control::Reply>,
+) {
+    let path = export_path("screenshot", "png");
+    let (w, h) = canvas.viewport().size();
+    let result = canvas
+        .read_pixels(None, PixelFormatEnum::ABGR8888) //RGBA byte order
+        .and_then(|pixels| screenshot::encode(w as usize, h as usize, &pixels, metadata))
+        .and_then(|png| std::fs::write(&path, png).map_err(|e| e.to_string()));
+    match &result {
+        Ok(()) => info!("Saved screenshot to {}", path),
+        Err(e) => warn!("Couldn't write {}: {}", path, e),
+    }
+    for reply in replies.drain(..) {
+        let answer = match &result {
+            Ok(()) => {
+                let full_path =
+                    std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone().into());
+                Ok(serde_json::json!({ "path": full_path }))
+            }
+            Err(e) => Err(format!("couldn't write {}: {}", path, e)),
+        };
+        let _ = reply.send(answer);
+    }
+}
+
+//SVG color attribute
+fn css(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+//File name in the current directory that won't collide with earlier exports,
+//an empty extension is for directories
+fn export_path(prefix: &str, extension: &str) -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let suffix = if extension.is_empty() {
+        String::new()
+    } else {
+        format!(".{}", extension)
+    };
+    let mut path = format!("{}-{}{}", prefix, secs, suffix);
+    let mut n = 1;
+    while Path::new(&path).exists() {
+        path = format!("{}-{}-{}{}", prefix, secs, n, suffix);
+        n += 1;
+    }
+    path
+}
+
+//Fast preview of the background.  Returns true if a slower anti-aliased pass
+//(refine_bg) should follow once the event queue is empty.
+fn update_bg(
+    bg_texture: &mut sdl2::render::Texture,
+    view: &ComplexBBox,
+    settings: &mut RenderSettings,
+    perf: &mut Perf,
+) -> bool {
     let TextureQuery {
         format: _,
         access: _,
@@ -671,48 +947,99 @@ fn update_bg(bg_texture: &mut sdl2::render::Texture, view: &ComplexBBox, iter: u
     let w: usize = width.try_into().unwrap();
     let h: usize = height.try_into().unwrap();
 
+    //emscripten target don't yet support multi-threading, see kernel.rs for the rayon rows
+    let before = Instant::now();
+    let counts = render::render(view, w, h, settings, INITIAL_ITERATIONS);
+    let iter = settings.iterations;
+    perf.render = RenderStats::new(before.elapsed(), &counts);
+    debug!(
+        target: "render",
+        "Render {}x{} ({} iterations, {}) time: {:?}",
+        w,
+        h,
+        iter,
+        settings.strategy.name(),
+        perf.render.time
+    );
+
     //maybe eventually cast u8 vector to u32 vector?
-    bg_texture
-        .with_lock(None, |pixel_buffer: &mut [u8], _pitch: usize| {
-            //TODO: farm this out to multiple threads
-            let rows: Vec<(usize, &mut [u8])> = pixel_buffer
-                .chunks_mut(w * 4) //4-bytes-per-pixel
-                .enumerate()
-                .collect();
+    if let Err(e) = bg_texture.with_lock(None, |pixel_buffer: &mut [u8], pitch: usize| {
+        kernel::colorize(&counts, iter, w, pixel_buffer, pitch);
+    }) {
+        //the last background stays up, and the next change of view tries again
+        warn!(target: "render", "Couldn't update the background: {}", e);
+        return false;
+    }
 
-            //change to .into_par_iter() for parallelism
-            //emscripten target don't yet support multi-threading
-            //#[cfg(not(target_os = "emscripten"))]
-            let row_iter = rows.into_par_iter();
-            //#[cfg(target_os = "emscripten")]
-            //let row_iter = rows.into_iter();
-            row_iter.for_each(|(y, buffer)| {
-                for x in 0..w {
-                    let c = view.screen_to_complex(
-                        x.try_into().unwrap(),
-                        y.try_into().unwrap(),
-                        w.try_into().unwrap(),
-                        h.try_into().unwrap(),
-                    );
-                    let mut z = Complex::<f64> { re: 0.0, im: 0.0 };
-
-                    for _i in 0..iter {
-                        z = z * z + c;
-                        if z.norm_sqr() > 4.0 {
-                            break;
-                        }
-                    }
+    settings.antialias != AntiAlias::Off
+}
+
+//An anti-aliased background, for the render numbered `generation`
+struct Refined {
+    generation: u64,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>, //laid out like the background texture
+}
 
-                    let color = if z.norm_sqr() > 4.0 { 255 } else { 0 };
-                    //let offset:usize = y * pitch + x * 4;
-                    let offset: usize = x * 4;
-                    buffer[offset + 0] = color; //Blue
-                    buffer[offset + 1] = color; //Green
-                    buffer[offset + 2] = color; //Red
-                    buffer[offset + 3] = 255; //Alpha
-                } //for x
-            }); //foreach y
-        })
-        .unwrap();
-    ()
+//Anti-alias the background on a worker, which sends it back and wakes the
+//main loop when it's done
+fn refine_bg(
+    bg_texture: &sdl2::render::Texture,
+    view: &ComplexBBox,
+    settings: &RenderSettings,
+    generation: u64,
+    sender: mpsc::Sender<Refined>,
+    wake: control::Wake,
+) {
+    let TextureQuery { width, height, .. } = bg_texture.query();
+    let (view, settings) = (*view, *settings);
+    let refine = move || {
+        let (w, h) = (width as usize, height as usize);
+        let mut pixels = vec![0; w * h * 4];
+        let before = Instant::now();
+        supersample::render(
+            &view,
+            w,
+            h,
+            settings.iterations,
+            settings.antialias,
+            &mut pixels,
+            w * 4,
+        );
+        debug!(
+            target: "render",
+            "Anti-aliasing ({}) time: {:?}",
+            settings.antialias.name(),
+            before.elapsed()
+        );
+        let refined = Refined {
+            generation,
+            width,
+            height,
+            pixels,
+        };
+        if sender.send(refined).is_ok() {
+            wake();
+        }
+    };
+    //emscripten target don't yet support multi-threading
+    #[cfg(target_os = "emscripten")]
+    refine();
+    #[cfg(not(target_os = "emscripten"))]
+    std::thread::spawn(refine);
+}
+
+//Replace the preview with its anti-aliased version, unless the view (or
+//window size) has changed since
+fn show_refined(bg_texture: &mut sdl2::render::Texture, refined: Refined, generation: u64) {
+    let TextureQuery { width, height, .. } = bg_texture.query();
+    if refined.generation != generation || (refined.width, refined.height) != (width, height) {
+        debug!(target: "render", "Dropped an out of date anti-aliased background");
+        return;
+    }
+    if let Err(e) = bg_texture.update(None, &refined.pixels, width as usize * 4) {
+        //the preview stays up
+        warn!(target: "render", "Couldn't anti-alias the background: {}", e);
+    }
 }
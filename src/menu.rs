@@ -1,6 +1,5 @@
 use sdl2;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::{Point, Rect};
 use sdl2::surface::Surface;
 use sdl2::ttf::Sdl2TtfContext;
@@ -10,35 +9,52 @@ use sdl2::{
 };
 use std::path::Path;
 
+use crate::config::Colors;
+use crate::error::Error;
+use crate::font;
+use crate::keymap::{Action, Input, Keymap};
+
 pub struct Menu<'a> {
     pub texture: Texture<'a>,
-    pub buttons: Vec<(Option<Keycode>, Rect, Texture<'a>)>,
+    pub buttons: Vec<(Action, Rect, Texture<'a>)>,
     //pub buttons: Vec<(String, Rect, Texture<'a>)>,
     pub offset_rect: Rect,
 }
 
 impl<'a> Menu<'a> {
-    pub fn init(tc: &'a TextureCreator<WindowContext>, ttf_context: &Sdl2TtfContext) -> Menu<'a> {
+    pub fn init(
+        tc: &'a TextureCreator<WindowContext>,
+        ttf_context: &Sdl2TtfContext,
+        font_path: Option<&Path>,
+        colors: &Colors,
+        keys: &Keymap,
+    ) -> Result<Menu<'a>, Error> {
         let padding = 10;
-        let bg_color = Color::RGBA(245, 245, 245, 230);
-
-        let mut menu_surface = Surface::new(250, 400, PixelFormatEnum::ARGB8888).unwrap();
-        menu_surface.fill_rect(None, bg_color).unwrap();
+        let bg_color = colors.menu_background;
 
         let mut buttons = Vec::new();
 
-        let font_path = Path::new("assets/DejaVuSansMono.ttf");
-        let button_font = ttf_context.load_font(font_path, 16).unwrap();
-        let hint_font = ttf_context.load_font(font_path, 14).unwrap();
+        let button_font = font::load(ttf_context, font_path, 16)?;
+        let hint_font = font::load(ttf_context, font_path, 14)?;
+
+        //hints go below the buttons, and the menu is as tall as both
+        let button_step = button_font.height() + 6;
+        let hint_step = hint_font.height() + 6;
+        let hints_y = menu_items(keys).len() as i32 * button_step + padding;
+        let menu_height = hints_y + hints(keys).len() as i32 * hint_step + padding;
+
+        let mut menu_surface =
+            Surface::new(250, menu_height as u32, PixelFormatEnum::ARGB8888).map_err(Error::Sdl)?;
+        menu_surface.fill_rect(None, bg_color).map_err(Error::Sdl)?;
 
-        let menu_text_color = Color::RGBA(240, 170, 0, 255);
-        let highlight_text_color = Color::RGBA(240, 170, 0, 255);
-        let highlight_bg_color = Color::RGBA(100, 0, 100, 255);
+        let menu_text_color = colors.menu_text;
+        let highlight_text_color = colors.menu_highlight_text;
+        let highlight_bg_color = colors.menu_highlight_background;
 
         let menu_offset_x = 10;
         let menu_offset_y = 10;
 
-        for (y, (message, key_binding)) in menu_items().iter().enumerate() {
+        for (y, (message, key_binding)) in menu_items(keys).iter().enumerate() {
             let plain_text: String = message.chars().filter(|x| *x != '_').collect();
             assert!(message.len() - plain_text.len() < 2); //Allow at most one "keyed"/underscored char per item
             let mut underscored: String = message
@@ -49,21 +65,17 @@ impl<'a> Menu<'a> {
 
             let mut normal_text = button_font
                 .render(plain_text.as_str())
-                .blended(menu_text_color)
-                .unwrap();
+                .blended(menu_text_color)?;
             let normal_underscored = button_font
                 .render(underscored.as_str())
-                .blended(menu_text_color)
-                .unwrap();
+                .blended(menu_text_color)?;
 
             let mut highlighted_text = button_font
                 .render(plain_text.as_str())
-                .blended(highlight_text_color)
-                .unwrap();
+                .blended(highlight_text_color)?;
             let highlighted_underscored = button_font
                 .render(underscored.as_str())
-                .blended(highlight_text_color)
-                .unwrap();
+                .blended(highlight_text_color)?;
 
             let (width, height) = normal_text.size();
             let displacement: i32 = y as i32 * (height + 6) as i32 + padding;
@@ -71,16 +83,16 @@ impl<'a> Menu<'a> {
 
             normal_underscored
                 .blit(None, &mut normal_text, Rect::new(0, 0, width, height))
-                .unwrap();
+                .map_err(Error::Sdl)?;
             highlighted_underscored
                 .blit(None, &mut highlighted_text, Rect::new(0, 0, width, height))
-                .unwrap();
+                .map_err(Error::Sdl)?;
 
             let mut highlighted_surface =
-                Surface::new(width, height, PixelFormatEnum::ARGB8888).unwrap();
+                Surface::new(width, height, PixelFormatEnum::ARGB8888).map_err(Error::Sdl)?;
             highlighted_surface
                 .fill_rect(None, highlight_bg_color)
-                .unwrap();
+                .map_err(Error::Sdl)?;
             //TODO: stick this in the "buttons", and copy to menu when hovering over
             highlighted_text
                 .blit(
@@ -88,8 +100,8 @@ impl<'a> Menu<'a> {
                     &mut highlighted_surface,
                     Rect::new(0, 0, width, height),
                 )
-                .unwrap();
-            let highlighted_texture = highlighted_surface.as_texture(tc).unwrap();
+                .map_err(Error::Sdl)?;
+            let highlighted_texture = highlighted_surface.as_texture(tc)?;
             let highlighted_rect = Rect::new(
                 padding + menu_offset_x,
                 displacement + menu_offset_y,
@@ -97,27 +109,28 @@ impl<'a> Menu<'a> {
                 height,
             );
 
-            buttons.push((key_binding.clone(), highlighted_rect, highlighted_texture));
+            buttons.push((*key_binding, highlighted_rect, highlighted_texture));
             normal_text
                 .blit(None, &mut menu_surface, normal_rect)
-                .unwrap();
+                .map_err(Error::Sdl)?;
 
             //highlighted_surface.blit(None, &mut menu_surface, normal_rect).unwrap();
         }
 
-        let hints_text_color = Color::RGBA(120, 120, 120, 255);
-        for (y, message) in hints().iter().enumerate() {
-            let m = hint_font.render(message).blended(hints_text_color).unwrap();
+        let hints_text_color = colors.menu_hint_text;
+        for (y, message) in hints(keys).iter().enumerate() {
+            let m = hint_font.render(message).blended(hints_text_color)?;
             let (width, height) = m.size();
-            let displacement: i32 = y as i32 * (height + 6) as i32 + 150;
+            let displacement: i32 = y as i32 * (height + 6) as i32 + hints_y;
             let m_rect = Rect::new(padding, displacement, width, height);
-            m.blit(None, &mut menu_surface, m_rect).unwrap();
+            m.blit(None, &mut menu_surface, m_rect)
+                .map_err(Error::Sdl)?;
         }
 
-        let menu_texture = menu_surface.as_texture(tc).unwrap();
+        let menu_texture = menu_surface.as_texture(tc)?;
         let menu_query = menu_texture.query();
 
-        Menu {
+        Ok(Menu {
             texture: menu_texture,
             buttons: buttons,
             offset_rect: Rect::new(
@@ -126,14 +139,10 @@ impl<'a> Menu<'a> {
                 menu_query.width,
                 menu_query.height,
             ),
-        }
+        })
     } //init
 
-    pub fn selected(
-        &self,
-        mouse_x: i32,
-        mouse_y: i32,
-    ) -> Option<&(Option<Keycode>, Rect, Texture)> {
+    pub fn selected(&self, mouse_x: i32, mouse_y: i32) -> Option<&(Action, Rect, Texture)> {
         let mouse_point = Point::new(mouse_x, mouse_y);
 
         for but in self.buttons.iter() {
@@ -146,33 +155,85 @@ impl<'a> Menu<'a> {
     }
 } //impl Menu
 
-fn menu_items() -> Vec<(&'static str, Option<sdl2::keyboard::Keycode>)> {
-    vec![
-        ("_Fullscreen", Some(Keycode::F)),
-        ("Display _Coordinates", Some(Keycode::C)),
-        ("_Menu", Some(Keycode::M)),
-        ("_Quit", Some(Keycode::Q)),
-        ("_About", Some(Keycode::A)),
-    ]
+const MENU: [(Action, &str); 10] = [
+    (Action::ToggleFullscreen, "Fullscreen"),
+    (Action::ToggleCoords, "Display Coordinates"),
+    (Action::TogglePerfHud, "Performance HUD"),
+    (Action::ExportCsv, "Export Orbits (CSV)"),
+    (Action::ExportJson, "Export Orbits (JSON)"),
+    (Action::ExportSvg, "Export SVG"),
+    (Action::Screenshot, "Screenshot"),
+    (Action::ToggleMenu, "Menu"),
+    (Action::Quit, "Quit"),
+    (Action::About, "About"),
+];
+
+//Labels with the bound key underscored, or named after the label when it
+//doesn't appear in it, so the menu follows the keybindings
+fn menu_items(keys: &Keymap) -> Vec<(String, Action)> {
+    MENU.iter()
+        .map(|(action, label)| {
+            let text = match keys.inputs(*action).first() {
+                Some(input) => {
+                    let name = input.name();
+                    let found = match input {
+                        Input::Key(_, false) if name.chars().count() == 1 => {
+                            label.to_lowercase().find(&name.to_lowercase())
+                        }
+                        _ => None,
+                    };
+                    match found {
+                        Some(i) => format!("{}_{}", &label[..i], &label[i..]),
+                        None => format!("{} ({})", label, name),
+                    }
+                }
+                None => label.to_string(),
+            };
+            (text, *action)
+        })
+        .collect()
 }
 
-fn hints() -> Vec<&'static str> {
-    vec![
-        "_________________________",
-        "Zoom:",
-        "  Scroll Wheel",
-        "  +,-",
-        "  Touch Pinch",
-        "Left Mouse Button -> Pan",
-        "Save Orbit:",
-        "  Right Mouse Button",
-        "  Spacebar",
-        "  Touch double tap",
-        "Arrow Keys -> Move cursor",
-    ]
+fn hints(keys: &Keymap) -> Vec<String> {
+    let key = |action| {
+        keys.inputs(action)
+            .iter()
+            .map(|i| i.name())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let mut hints = vec![
+        "_________________________".to_string(),
+        "Zoom:".to_string(),
+        "  Scroll Wheel".to_string(),
+        format!("  {} / {}", key(Action::ZoomIn), key(Action::ZoomOut)),
+        "  Touch Pinch".to_string(),
+        "Left Mouse Button -> Pan".to_string(),
+        "Save Orbit:".to_string(),
+    ];
+    hints.extend(
+        keys.inputs(Action::SaveOrbit)
+            .iter()
+            .map(|i| format!("  {}", i.name())),
+    );
+    hints.extend(vec![
+        "  Touch double tap".to_string(),
+        format!("{} -> Clear saved", key(Action::ClearOrbits)),
+        "Arrow Keys -> Move cursor".to_string(),
+        "Drop a screenshot or".to_string(),
+        "  .kfr/.xpf/.par -> Restore".to_string(),
+        format!("{} -> Export location", key(Action::ExportLocation)),
+        format!("{} -> Zoom sequence frames", key(Action::ZoomSequence)),
+        format!("{} -> Script console", key(Action::ToggleConsole)),
+        format!(
+            "{} -> goto/orbit/iter/radius/open/zoom",
+            key(Action::CommandLine)
+        ),
+    ]);
+    hints
 }
 
-fn about() -> Vec<&'static str> {
+pub fn about() -> Vec<&'static str> {
     vec![
         "Orbits --",
         "  A Mandelbrot set investigation tool",
@@ -188,3 +249,49 @@ fn about() -> Vec<&'static str> {
         "  ",
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_follows_the_keybindings() {
+        let labels = |keys: &Keymap| -> Vec<String> {
+            menu_items(keys)
+                .into_iter()
+                .map(|(label, _)| label)
+                .collect()
+        };
+        let bind = |keys: &mut Keymap, action: &str, key: &str| {
+            keys.bind(action, &[key.to_string()]).unwrap()
+        };
+        let mut keys = Keymap::default();
+        assert_eq!(
+            labels(&keys),
+            vec![
+                "_Fullscreen",
+                "Display _Coordinates",
+                "Performance _HUD",
+                "_Export Orbits (CSV)",
+                "Export Orbits (_JSON)",
+                "Export S_VG",
+                "Screenshot (F12)",
+                "_Menu",
+                "_Quit",
+                "_About",
+            ]
+        );
+
+        bind(&mut keys, "quit", "x");
+        bind(&mut keys, "export_location", "F2");
+        bind(&mut keys, "menu", "Tab");
+        assert_eq!(labels(&keys)[7..9], ["Menu (Tab)", "Quit (X)"]);
+        bind(&mut keys, "auto_iterations", "F3");
+        bind(&mut keys, "quit", "u");
+        keys.validate().unwrap();
+        assert_eq!(labels(&keys)[8], "Q_uit");
+        assert!(hints(&keys).contains(&"F2 -> Export location".to_string()));
+        keys.bind("about", &[]).unwrap();
+        assert_eq!(labels(&keys)[9], "About");
+    }
+}
@@ -0,0 +1,61 @@
+//Orbits of 0 under z -> z^2 + c
+use num::Complex;
+use serde::Serialize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct Bailout {
+    pub max_iterations: u32,
+    pub radius: f64, //the orbit has escaped once |z| is larger than this
+}
+
+impl Default for Bailout {
+    fn default() -> Bailout {
+        Bailout {
+            max_iterations: 50,
+            radius: 2.0,
+        }
+    }
+}
+
+//z_0 = 0, z_1 = c, z_2, ... up to the last iterate still inside the bailout radius
+pub fn calc_orbits(c: Complex<f64>, bailout: &Bailout) -> Vec<Complex<f64>> {
+    orbit_rows(c, bailout)
+        .into_iter()
+        .take_while(|row| !row.escaped)
+        .map(|row| Complex::new(row.re, row.im))
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct OrbitRow {
+    pub index: u32,
+    pub re: f64,
+    pub im: f64,
+    pub abs: f64,
+    pub escaped: bool,
+}
+
+//Every iterate of the orbit, including the first one outside the bailout
+//radius (flagged as escaped) if there is one
+pub fn orbit_rows(c: Complex<f64>, bailout: &Bailout) -> Vec<OrbitRow> {
+    let limit_sqr = bailout.radius * bailout.radius;
+    let mut z = Complex { re: 0.0, im: 0.0 };
+    let mut rows = Vec::new();
+
+    for index in 0..=bailout.max_iterations {
+        let escaped = z.norm_sqr() > limit_sqr;
+        rows.push(OrbitRow {
+            index,
+            re: z.re,
+            im: z.im,
+            abs: z.norm(),
+            escaped,
+        });
+        if escaped {
+            break;
+        }
+        z = z * z + c;
+    }
+
+    rows
+}
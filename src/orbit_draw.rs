@@ -0,0 +1,213 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Canvas, TextureCreator, TextureQuery};
+use sdl2::ttf::Font;
+use sdl2::video::{Window, WindowContext};
+
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+use orbits::orbit_style::OrbitStyle;
+
+fn lerp_color(c1: Color, c2: Color, t: f64) -> Color {
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    Color::RGBA(
+        mix(c1.r, c2.r),
+        mix(c1.g, c2.g),
+        mix(c1.b, c2.b),
+        mix(c1.a, c2.a),
+    )
+}
+
+//color of the segment (or marker) for iterate `i` of `n`
+fn iterate_color(style: &OrbitStyle, c1: Color, c2: Color, i: usize, n: usize) -> Color {
+    if style.gradient {
+        lerp_color(c1, c2, i as f64 / (n.max(2) - 1) as f64)
+    } else if i == 0 {
+        c1
+    } else {
+        c2
+    }
+}
+
+//Line from p1 to p2 of the given width.  Walks along the major axis and fills
+//the pixels across it whose centers lie within half the width of the line,
+//with partial coverage turned into alpha when anti-aliasing.  The pixels are
+//drawn together, one draw_points for each alpha.
+fn draw_segment(
+    canvas: &mut Canvas<Window>,
+    p1: Point,
+    p2: Point,
+    color: Color,
+    thickness: u32,
+    antialias: bool,
+) -> Result<(), String> {
+    if thickness <= 1 && !antialias {
+        canvas.set_draw_color(color);
+        return canvas.draw_line(p1, p2);
+    }
+
+    let (x1, y1, x2, y2) = (p1.x() as f64, p1.y() as f64, p2.x() as f64, p2.y() as f64);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let steep = dy.abs() > dx.abs();
+    //swap axes for steep lines so we always step along the major axis
+    let (a1, b1, da, db) = if steep {
+        (y1, x1, dy, dx)
+    } else {
+        (x1, y1, dx, dy)
+    };
+    let (a_start, a_end) = if da >= 0.0 {
+        (a1, a1 + da)
+    } else {
+        (a1 + da, a1)
+    };
+    let slope = if da == 0.0 { 0.0 } else { db / da };
+    //distance across the line per pixel along the minor axis
+    let cos = if length == 0.0 {
+        1.0
+    } else {
+        da.abs() / length
+    };
+    let half_width = thickness as f64 / 2.0;
+
+    //only walk the part of the line that can touch the window, orbits of
+    //deeply zoomed views go far off screen
+    let (win_w, win_h) = canvas.viewport().size();
+    let a_limit = (if steep { win_h } else { win_w }) as f64 + half_width + 1.0;
+    let a_end = a_end.min(a_limit);
+
+    let mut by_alpha: BTreeMap<u8, Vec<Point>> = BTreeMap::new();
+    let mut a = a_start.max(-half_width - 1.0).round();
+    while a <= a_end {
+        let b_center = b1 + (a - a1) * slope;
+        let reach = half_width / cos + 1.0;
+        let mut b = (b_center - reach).floor();
+        while b <= b_center + reach {
+            let distance = (b - b_center).abs() * cos;
+            let coverage = if antialias {
+                (half_width + 0.5 - distance).clamp(0.0, 1.0)
+            } else if distance <= half_width.max(0.5) {
+                1.0
+            } else {
+                0.0
+            };
+            if coverage > 0.0 {
+                let alpha = (color.a as f64 * coverage).round() as u8;
+                let (x, y) = if steep { (b, a) } else { (a, b) };
+                by_alpha
+                    .entry(alpha)
+                    .or_default()
+                    .push(Point::new(x as i32, y as i32));
+            }
+            b += 1.0;
+        }
+        a += 1.0;
+    }
+    for (alpha, points) in by_alpha {
+        canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, alpha));
+        canvas.draw_points(points.as_slice())?;
+    }
+    Ok(())
+}
+
+pub fn draw_orbits(
+    canvas: &mut Canvas<Window>,
+    creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    ps: &[Point],
+    c1: Color,
+    c2: Color,
+    style: &OrbitStyle,
+) -> Result<(), String> {
+    draw(canvas, creator, font, ps, c1, c2, style, None)
+}
+
+//Like draw_orbits, but only the last `trail` segments are drawn, fading out
+//with age, and the newest point is always marked
+#[allow(clippy::too_many_arguments)]
+pub fn draw_orbit_trail(
+    canvas: &mut Canvas<Window>,
+    creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    ps: &[Point],
+    c1: Color,
+    c2: Color,
+    style: &OrbitStyle,
+    trail: usize,
+) -> Result<(), String> {
+    draw(canvas, creator, font, ps, c1, c2, style, Some(trail))?;
+    if let Some(head) = ps.last() {
+        canvas.set_draw_color(iterate_color(style, c1, c2, ps.len() - 1, ps.len()));
+        let size = style.thickness + 5;
+        let mut dot = Rect::new(0, 0, size, size);
+        dot.center_on(*head);
+        canvas.fill_rect(dot)?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw(
+    canvas: &mut Canvas<Window>,
+    creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    ps: &[Point],
+    c1: Color,
+    c2: Color,
+    style: &OrbitStyle,
+    fade: Option<usize>,
+) -> Result<(), String> {
+    let n = ps.len();
+    let blend = style.antialias || fade.is_some();
+    canvas.set_blend_mode(if blend {
+        BlendMode::Blend
+    } else {
+        BlendMode::None
+    });
+
+    let segments = n.saturating_sub(1);
+    for (i, (p1, p2)) in ps.iter().tuple_windows().enumerate() {
+        let mut color = iterate_color(style, c1, c2, i, n);
+        if let Some(trail) = fade {
+            let age = segments - 1 - i;
+            if age >= trail {
+                continue;
+            }
+            color.a = (color.a as f64 * (1.0 - age as f64 / trail as f64)).round() as u8;
+        }
+        draw_segment(canvas, *p1, *p2, color, style.thickness, style.antialias)?;
+    }
+    canvas.set_blend_mode(BlendMode::None);
+
+    if style.markers {
+        let size = style.thickness + 3;
+        for (i, p) in ps.iter().enumerate() {
+            canvas.set_draw_color(iterate_color(style, c1, c2, i, n));
+            let mut dot = Rect::new(0, 0, size, size);
+            dot.center_on(*p);
+            canvas.fill_rect(dot)?;
+        }
+    }
+
+    for (i, p) in ps.iter().enumerate().take(style.numbered) {
+        let label = font
+            .render(&i.to_string())
+            .blended(iterate_color(style, c1, c2, i, n))
+            .map_err(|e| e.to_string())?;
+        let texture = creator
+            .create_texture_from_surface(&label)
+            .map_err(|e| e.to_string())?;
+        let TextureQuery { width, height, .. } = texture.query();
+        let offset = style.thickness as i32 + 3;
+        let rect = Rect::new(
+            p.x() + offset,
+            p.y() - offset - height as i32,
+            width,
+            height,
+        );
+        canvas.copy(&texture, None, rect)?;
+    }
+
+    Ok(())
+}
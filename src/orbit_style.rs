@@ -0,0 +1,40 @@
+//How orbits are drawn, switched from the keyboard
+const THICKNESSES: [u32; 4] = [1, 2, 3, 5];
+const NUMBERED: [usize; 3] = [0, 10, 25];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitStyle {
+    pub thickness: u32, //line width in pixels
+    pub antialias: bool,
+    pub markers: bool,   //dot at each iterate
+    pub numbered: usize, //label the first `numbered` iterates with their index
+    pub gradient: bool,  //blend from the first to the second color along the orbit
+}
+
+impl Default for OrbitStyle {
+    //plain one pixel lines, first segment in the first color and the rest in the second
+    fn default() -> OrbitStyle {
+        OrbitStyle {
+            thickness: 1,
+            antialias: false,
+            markers: false,
+            numbered: 0,
+            gradient: false,
+        }
+    }
+}
+
+impl OrbitStyle {
+    pub fn next_thickness(&mut self) {
+        self.thickness = next_in(&THICKNESSES, self.thickness);
+    }
+
+    pub fn next_numbered(&mut self) {
+        self.numbered = next_in(&NUMBERED, self.numbered);
+    }
+}
+
+fn next_in<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    let i = choices.iter().position(|x| *x == current).unwrap_or(0);
+    choices[(i + 1) % choices.len()]
+}
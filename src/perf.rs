@@ -0,0 +1,79 @@
+//Timings for the performance HUD, to compare kernels and render strategies
+use std::time::Duration;
+
+use orbits::render::RenderSettings;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RenderStats {
+    pub time: Duration,
+    pub pixels: usize,
+    //sum of the escape times, subdivide fills whole rectangles without
+    //iterating them so this is an upper bound there
+    pub iterations: u64,
+}
+
+impl RenderStats {
+    pub fn new(time: Duration, counts: &[u32]) -> RenderStats {
+        RenderStats {
+            time,
+            pixels: counts.len(),
+            iterations: counts.iter().map(|&c| c as u64).sum(),
+        }
+    }
+
+    pub fn pixels_per_second(&self) -> f64 {
+        self.pixels as f64 / self.time.as_secs_f64().max(1e-9)
+    }
+}
+
+#[derive(Default)]
+pub struct Perf {
+    pub render: RenderStats, //last update_bg
+    pub overlay: Duration,   //drawing the orbits on the last frame
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+impl Perf {
+    pub fn hud_lines(&self, settings: &RenderSettings, threads: usize) -> Vec<String> {
+        vec![
+            format!(
+                "render: {:.1} ms  {:.1} Mpixels/s  ({}, {}, {} threads)",
+                ms(self.render.time),
+                self.render.pixels_per_second() / 1e6,
+                settings.strategy.name(),
+                settings.kernel.name(),
+                threads
+            ),
+            format!(
+                "{} iterations total, limit {}",
+                self.render.iterations, settings.iterations
+            ),
+            format!("orbit overlay: {:.2} ms", ms(self.overlay)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hud_shows_rates_and_totals() {
+        let perf = Perf {
+            render: RenderStats::new(Duration::from_millis(4), &[1, 2, 3, 50]),
+            overlay: Duration::from_micros(1500),
+        };
+        assert_eq!(perf.render.pixels_per_second(), 1000.0);
+        assert_eq!(
+            perf.hud_lines(&RenderSettings::new(50), 8),
+            vec![
+                "render: 4.0 ms  0.0 Mpixels/s  (brute force, packed4, 8 threads)",
+                "56 iterations total, limit 50",
+                "orbit overlay: 1.50 ms",
+            ]
+        );
+    }
+}
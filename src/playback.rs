@@ -0,0 +1,173 @@
+//Reveal an orbit one iterate at a time
+use num::Complex;
+use std::time::{Duration, Instant};
+
+const INITIAL_SPEED: f64 = 4.0; //iterates per second
+const MAX_SPEED: f64 = 256.0;
+const MIN_SPEED: f64 = 0.25;
+pub const TRAIL: usize = 12; //segments behind the newest iterate that stay visible
+
+pub struct Playback {
+    pub seed: Complex<f64>, //the parameter c of the orbit being played
+    pub index: usize,       //newest iterate shown
+    pub paused: bool,
+    pub speed: f64,
+    last_step: Instant,
+}
+
+impl Playback {
+    pub fn new(seed: Complex<f64>) -> Playback {
+        Playback {
+            seed,
+            index: 0,
+            paused: false,
+            speed: INITIAL_SPEED,
+            last_step: Instant::now(),
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.speed)
+    }
+
+    //keep to an orbit of `len` points, for when the orbit got shorter
+    pub fn fit(&mut self, len: usize) {
+        self.index = self.index.min(len.saturating_sub(1));
+    }
+
+    //move forward by however many iterates are due `now`, stopping at the end
+    //of an orbit with `len` points
+    pub fn advance(&mut self, len: usize, now: Instant) {
+        self.fit(len);
+        if self.paused {
+            return;
+        }
+        let last = len.saturating_sub(1);
+        while self.index < last && now.saturating_duration_since(self.last_step) >= self.interval()
+        {
+            self.index += 1;
+            self.last_step += self.interval();
+        }
+        if self.index >= last {
+            self.index = last;
+            self.paused = true;
+        }
+    }
+
+    //how long the main loop may sleep before the next iterate is due, None
+    //when nothing is animating
+    pub fn time_to_next_step(&self, now: Instant) -> Option<Duration> {
+        if self.paused {
+            None
+        } else {
+            Some(
+                self.interval()
+                    .saturating_sub(now.saturating_duration_since(self.last_step)),
+            )
+        }
+    }
+
+    pub fn toggle_pause(&mut self, len: usize) {
+        if self.paused && self.index + 1 >= len {
+            self.index = 0; //start over once the end has been reached
+        }
+        self.paused = !self.paused;
+        self.last_step = Instant::now();
+    }
+
+    pub fn step_forward(&mut self, len: usize) {
+        self.paused = true;
+        self.index = (self.index + 1).min(len.saturating_sub(1));
+    }
+
+    pub fn step_back(&mut self) {
+        self.paused = true;
+        self.index = self.index.saturating_sub(1);
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(MIN_SPEED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playback() -> Playback {
+        Playback::new(Complex::new(-1.0, 0.0))
+    }
+
+    //`secs` after the last iterate was shown
+    fn after(p: &Playback, secs: f64) -> Instant {
+        p.last_step + Duration::from_secs_f64(secs)
+    }
+
+    #[test]
+    fn stepping_stops_at_both_ends() {
+        let mut p = playback();
+        p.step_back();
+        assert_eq!((p.index, p.paused), (0, true));
+        for _ in 0..5 {
+            p.step_forward(3);
+        }
+        assert_eq!(p.index, 2);
+        p.step_back();
+        assert_eq!(p.index, 1);
+        //an orbit with nothing to show
+        p.step_forward(0);
+        assert_eq!(p.index, 0);
+
+        //an orbit that got shorter while paused
+        p.index = 8;
+        p.advance(3, after(&p, 0.0));
+        assert_eq!((p.index, p.paused), (2, true));
+    }
+
+    #[test]
+    fn iterates_come_at_the_speed_until_paused_or_done() {
+        let mut p = playback();
+        //four a second
+        let now = after(&p, 1.1);
+        p.advance(100, now);
+        assert_eq!(p.index, 4);
+        assert_eq!(
+            p.time_to_next_step(now),
+            Some(Duration::from_secs_f64(0.15))
+        );
+
+        p.toggle_pause(100);
+        assert_eq!(p.time_to_next_step(now), None);
+        p.advance(100, after(&p, 10.0));
+        assert_eq!(p.index, 4);
+        //resuming starts the wait for the next iterate over
+        p.toggle_pause(100);
+        p.advance(100, after(&p, 0.2));
+        assert_eq!((p.index, p.paused), (4, false));
+
+        p.advance(10, after(&p, 100.0));
+        assert_eq!((p.index, p.paused), (9, true));
+        //and playing again from the end starts over
+        p.toggle_pause(10);
+        assert_eq!((p.index, p.paused), (0, false));
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        let mut p = playback();
+        for _ in 0..20 {
+            p.faster();
+        }
+        assert_eq!(p.speed, MAX_SPEED);
+        for _ in 0..20 {
+            p.slower();
+        }
+        assert_eq!(p.speed, MIN_SPEED);
+        p.faster();
+        assert_eq!(p.speed, MIN_SPEED * 2.0);
+    }
+}
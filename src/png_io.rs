@@ -0,0 +1,49 @@
+//PNG encoding for exported images, with optional tEXt metadata
+use std::convert::TryInto;
+
+//PNG file contents for a `w` x `h` image of RGBA bytes
+pub fn encode_rgba(w: usize, h: usize, rgba: &[u8]) -> Result<Vec<u8>, String> {
+    encode_rgba_with_text(w, h, rgba, &[])
+}
+
+//Same as encode_rgba, with a tEXt chunk for each (keyword, text) pair
+pub fn encode_rgba_with_text(
+    w: usize,
+    h: usize,
+    rgba: &[u8],
+    text: &[(String, String)],
+) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(
+            &mut bytes,
+            w.try_into().map_err(|_| "image too wide")?,
+            h.try_into().map_err(|_| "image too tall")?,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        for (keyword, value) in text {
+            encoder
+                .add_text_chunk(keyword.clone(), value.clone())
+                .map_err(|e| e.to_string())?;
+        }
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer.write_image_data(rgba).map_err(|e| e.to_string())?;
+    }
+    Ok(bytes)
+}
+
+//(keyword, text) of every tEXt chunk in a PNG file
+pub fn read_text(bytes: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let decoder = png::Decoder::new(bytes);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut image = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut image).map_err(|e| e.to_string())?;
+    reader.finish().map_err(|e| e.to_string())?; //text after the image data
+    Ok(reader
+        .info()
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect())
+}
@@ -0,0 +1,567 @@
+//Recording and replay of the input the main loop consumes, one JSON object
+//per line with the milliseconds since the start, e.g.
+//
+//    {"t":0,"event":"mouse_motion","x":200,"y":150,"xrel":0,"yrel":0,"left":false}
+//    {"t":480,"event":"key_down","key":"Space","shift":false}
+//    {"t":900,"event":"quit"}
+//
+//Replaying under SDL's dummy video driver (SDL_VIDEODRIVER=dummy) with the
+//same command line reproduces the session, and --dump-state writes where it
+//ended up, see tests/replay.rs.
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::{MouseButton, MouseState, MouseWheelDirection};
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use crate::error::Error;
+use crate::keymap;
+use crate::SDL_TOUCH_MOUSEID;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Recorded {
+    Quit,
+    KeyDown {
+        key: String,
+        shift: bool,
+    },
+    KeyUp {
+        key: String,
+        shift: bool,
+    },
+    MouseDown {
+        button: String,
+        x: i32,
+        y: i32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        touch: bool,
+    },
+    MouseUp {
+        button: String,
+        x: i32,
+        y: i32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        touch: bool,
+    },
+    MouseMotion {
+        x: i32,
+        y: i32,
+        xrel: i32,
+        yrel: i32,
+        left: bool, //button held, i.e. dragging
+        #[serde(default, skip_serializing_if = "is_false")]
+        touch: bool, //made up by SDL from a touch
+    },
+    MouseWheel {
+        y: i32,
+    },
+    FingerUp,
+    MultiGesture {
+        x: f32,
+        y: f32,
+        d_dist: f32,
+        num_fingers: u16,
+    },
+    DropFile {
+        filename: String,
+    },
+    TextInput {
+        text: String,
+    },
+    Resized {
+        width: u32,
+        height: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Line {
+    pub t: u64, //milliseconds since the start
+    #[serde(flatten)]
+    pub event: Recorded,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+fn mouse_id(touch: bool) -> u32 {
+    if touch {
+        SDL_TOUCH_MOUSEID
+    } else {
+        0
+    }
+}
+
+const BUTTONS: [(&str, MouseButton); 5] = [
+    ("left", MouseButton::Left),
+    ("right", MouseButton::Right),
+    ("middle", MouseButton::Middle),
+    ("x1", MouseButton::X1),
+    ("x2", MouseButton::X2),
+];
+
+fn button_name(button: MouseButton) -> Option<String> {
+    BUTTONS
+        .iter()
+        .find(|(_, b)| *b == button)
+        .map(|(n, _)| n.to_string())
+}
+
+fn parse_button(name: &str) -> Result<MouseButton, String> {
+    BUTTONS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, b)| *b)
+        .ok_or(format!("unknown mouse button \"{}\"", name))
+}
+
+//Key names as in the config file, or "#<keycode>" for keys without one
+fn key_name(key: Keycode) -> String {
+    let name = keymap::key_name(key);
+    match keymap::parse_key(&name) {
+        Ok(parsed) if parsed == key => name,
+        _ => format!("#{}", key as i32),
+    }
+}
+
+fn parse_key(name: &str) -> Result<Keycode, String> {
+    match name.strip_prefix('#') {
+        Some(code) => code
+            .parse()
+            .ok()
+            .and_then(Keycode::from_i32)
+            .ok_or(format!("unknown keycode \"{}\"", name)),
+        None => keymap::parse_key(name),
+    }
+}
+
+fn shift_mod(shift: bool) -> Mod {
+    if shift {
+        Mod::LSHIFTMOD
+    } else {
+        Mod::NOMOD
+    }
+}
+
+impl Recorded {
+    //The parts of `event` the main loop looks at, None for events it ignores
+    pub fn from_event(event: &Event) -> Option<Recorded> {
+        let shifted = |keymod: &Mod| keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+        Some(match event {
+            Event::Quit { .. } => Recorded::Quit,
+            Event::KeyDown {
+                keycode: Some(key),
+                keymod,
+                ..
+            } => Recorded::KeyDown {
+                key: key_name(*key),
+                shift: shifted(keymod),
+            },
+            Event::KeyUp {
+                keycode: Some(key),
+                keymod,
+                ..
+            } => Recorded::KeyUp {
+                key: key_name(*key),
+                shift: shifted(keymod),
+            },
+            Event::MouseButtonDown {
+                which,
+                mouse_btn,
+                x,
+                y,
+                ..
+            } => Recorded::MouseDown {
+                button: button_name(*mouse_btn)?,
+                x: *x,
+                y: *y,
+                touch: *which == SDL_TOUCH_MOUSEID,
+            },
+            Event::MouseButtonUp {
+                which,
+                mouse_btn,
+                x,
+                y,
+                ..
+            } => Recorded::MouseUp {
+                button: button_name(*mouse_btn)?,
+                x: *x,
+                y: *y,
+                touch: *which == SDL_TOUCH_MOUSEID,
+            },
+            Event::MouseMotion {
+                which,
+                mousestate,
+                x,
+                y,
+                xrel,
+                yrel,
+                ..
+            } => Recorded::MouseMotion {
+                x: *x,
+                y: *y,
+                xrel: *xrel,
+                yrel: *yrel,
+                left: mousestate.left(),
+                touch: *which == SDL_TOUCH_MOUSEID,
+            },
+            Event::MouseWheel { y, .. } => Recorded::MouseWheel { y: *y },
+            Event::FingerUp { .. } => Recorded::FingerUp,
+            Event::MultiGesture {
+                x,
+                y,
+                d_dist,
+                num_fingers,
+                ..
+            } => Recorded::MultiGesture {
+                x: *x,
+                y: *y,
+                d_dist: *d_dist,
+                num_fingers: *num_fingers,
+            },
+            Event::DropFile { filename, .. } => Recorded::DropFile {
+                filename: filename.clone(),
+            },
+            Event::TextInput { text, .. } => Recorded::TextInput { text: text.clone() },
+            Event::Window {
+                win_event: WindowEvent::SizeChanged(width, height),
+                ..
+            } => Recorded::Resized {
+                width: *width as u32,
+                height: *height as u32,
+            },
+            _ => return None,
+        })
+    }
+
+    //The SDL event to feed the main loop.  Resizes are done to the window
+    //instead, which then sends its own event.
+    pub fn to_event(&self, timestamp: u32, window_id: u32) -> Result<Option<Event>, String> {
+        let mouse_button = |button: &str, x: i32, y: i32, touch: bool, down: bool| {
+            let (mouse_btn, which, clicks) = (parse_button(button)?, mouse_id(touch), 1);
+            Ok::<_, String>(if down {
+                Event::MouseButtonDown {
+                    timestamp,
+                    window_id,
+                    which,
+                    mouse_btn,
+                    clicks,
+                    x,
+                    y,
+                }
+            } else {
+                Event::MouseButtonUp {
+                    timestamp,
+                    window_id,
+                    which,
+                    mouse_btn,
+                    clicks,
+                    x,
+                    y,
+                }
+            })
+        };
+        Ok(Some(match self {
+            Recorded::Quit => Event::Quit { timestamp },
+            Recorded::KeyDown { key, shift } => Event::KeyDown {
+                timestamp,
+                window_id,
+                keycode: Some(parse_key(key)?),
+                scancode: None,
+                keymod: shift_mod(*shift),
+                repeat: false,
+            },
+            Recorded::KeyUp { key, shift } => Event::KeyUp {
+                timestamp,
+                window_id,
+                keycode: Some(parse_key(key)?),
+                scancode: None,
+                keymod: shift_mod(*shift),
+                repeat: false,
+            },
+            Recorded::MouseDown {
+                button,
+                x,
+                y,
+                touch,
+            } => mouse_button(button, *x, *y, *touch, true)?,
+            Recorded::MouseUp {
+                button,
+                x,
+                y,
+                touch,
+            } => mouse_button(button, *x, *y, *touch, false)?,
+            Recorded::MouseMotion {
+                x,
+                y,
+                xrel,
+                yrel,
+                left,
+                touch,
+            } => Event::MouseMotion {
+                timestamp,
+                window_id,
+                which: mouse_id(*touch),
+                mousestate: MouseState::from_sdl_state(if *left { 1 } else { 0 }),
+                x: *x,
+                y: *y,
+                xrel: *xrel,
+                yrel: *yrel,
+            },
+            Recorded::MouseWheel { y } => Event::MouseWheel {
+                timestamp,
+                window_id,
+                which: 0,
+                x: 0,
+                y: *y,
+                direction: MouseWheelDirection::Normal,
+            },
+            Recorded::FingerUp => Event::FingerUp {
+                timestamp,
+                touch_id: 0,
+                finger_id: 0,
+                x: 0.0,
+                y: 0.0,
+                dx: 0.0,
+                dy: 0.0,
+                pressure: 0.0,
+            },
+            Recorded::MultiGesture {
+                x,
+                y,
+                d_dist,
+                num_fingers,
+            } => Event::MultiGesture {
+                timestamp,
+                touch_id: 0,
+                d_theta: 0.0,
+                d_dist: *d_dist,
+                x: *x,
+                y: *y,
+                num_fingers: *num_fingers,
+            },
+            Recorded::DropFile { filename } => Event::DropFile {
+                timestamp,
+                window_id,
+                filename: filename.clone(),
+            },
+            Recorded::TextInput { text } => Event::TextInput {
+                timestamp,
+                window_id,
+                text: text.clone(),
+            },
+            Recorded::Resized { .. } => return Ok(None),
+        }))
+    }
+}
+
+pub struct Recorder {
+    file: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Recorder> {
+        Ok(Recorder {
+            file: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        match Recorded::from_event(event) {
+            Some(event) => self.write(event),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write(&mut self, event: Recorded) -> io::Result<()> {
+        let line = Line {
+            t: self.start.elapsed().as_millis() as u64,
+            event,
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&line)?)?;
+        //flushed as it goes, so a crash still leaves the steps leading up to it
+        self.file.flush()
+    }
+}
+
+//Times are checked here as well as events, so a hand edited recording can't
+//overflow an Instant or SDL's u32 timestamps while it plays
+pub fn parse(text: &str) -> Result<Vec<Line>, String> {
+    let mut last_t = 0;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let line: Line =
+                serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            if line.t > u32::MAX as u64 {
+                return Err(format!(
+                    "line {}: t {} is more than {}",
+                    i + 1,
+                    line.t,
+                    u32::MAX
+                ));
+            }
+            if line.t < last_t {
+                return Err(format!(
+                    "line {}: t {} is earlier than the previous line's {}",
+                    i + 1,
+                    line.t,
+                    last_t
+                ));
+            }
+            last_t = line.t;
+            line.event
+                .to_event(0, 0)
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            Ok(line)
+        })
+        .collect()
+}
+
+pub struct Replay {
+    lines: VecDeque<Line>,
+    start: Instant,
+}
+
+//Window events (and the control socket's) still come from SDL during a
+//replay, the rest of the real input is dropped
+fn passed_through(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Quit { .. } | Event::Window { .. } | Event::User { .. }
+    )
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Replay, Error> {
+        let text = std::fs::read_to_string(path)?;
+        let lines =
+            parse(&text).map_err(|e| Error::Replay(format!("{}: {}", path.display(), e)))?;
+        Ok(Replay {
+            lines: lines.into(),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn finished(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    //The next event once its time has come, waiting up to `wait` for it (None
+    //waits as long as it takes).  None when nothing is due in time, or the
+    //recording has run out.
+    pub fn next(
+        &mut self,
+        pump: &mut EventPump,
+        canvas: &mut Canvas<Window>,
+        wait: Option<Duration>,
+    ) -> Result<Option<Event>, Error> {
+        let give_up = wait.map(|wait| Instant::now() + wait);
+        loop {
+            while let Some(event) = pump.poll_event() {
+                if passed_through(&event) {
+                    return Ok(Some(event));
+                }
+            }
+            let line = match self.lines.front() {
+                Some(line) => line,
+                None => return Ok(None),
+            };
+            let due = self.start + Duration::from_millis(line.t);
+            let now = Instant::now();
+            if now < due {
+                let until = give_up.map_or(due, |give_up| give_up.min(due));
+                if until <= now {
+                    return Ok(None);
+                }
+                let timeout = (until - now).as_millis().max(1);
+                match pump.wait_event_timeout(timeout.try_into().unwrap_or(u32::MAX)) {
+                    Some(event) if passed_through(&event) => return Ok(Some(event)),
+                    _ => continue,
+                }
+            }
+            let line = self.lines.pop_front().unwrap();
+            let event = line
+                .event
+                .to_event(line.t as u32, canvas.window().id())
+                .map_err(Error::Replay)?;
+            match (event, line.event) {
+                (Some(event), _) => return Ok(Some(event)),
+                (None, Recorded::Resized { width, height }) => {
+                    //SDL sends the SizeChanged (unless it's that size already)
+                    if canvas.window().size() != (width, height) {
+                        canvas.window_mut().set_size(width, height)?;
+                    }
+                }
+                (None, _) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_survive_a_round_trip() {
+        let text = r##"{"t":0,"event":"mouse_motion","x":200,"y":150,"xrel":3,"yrel":-1,"left":true}
+{"t":15,"event":"key_down","key":"Space","shift":false}
+{"t":20,"event":"key_up","key":"#1073742049","shift":true}
+{"t":40,"event":"mouse_down","button":"right","x":5,"y":6}
+{"t":41,"event":"mouse_up","button":"left","x":5,"y":6,"touch":true}
+{"t":70,"event":"multi_gesture","x":0.5,"y":0.25,"d_dist":0.025,"num_fingers":2}
+{"t":90,"event":"resized","width":640,"height":480}
+
+{"t":99,"event":"quit"}
+"##;
+        let lines = parse(text).unwrap();
+        assert_eq!(lines.len(), 8);
+        for line in &lines {
+            let event = line.event.to_event(line.t as u32, 1).unwrap();
+            match event {
+                Some(event) => assert_eq!(Recorded::from_event(&event).as_ref(), Some(&line.event)),
+                None => assert_eq!(
+                    line.event,
+                    Recorded::Resized {
+                        width: 640,
+                        height: 480
+                    }
+                ),
+            }
+            let json = serde_json::to_string(line).unwrap();
+            assert_eq!(&parse(&json).unwrap()[0], line);
+        }
+        assert_eq!(key_name(Keycode::LShift), "#1073742049");
+        assert_eq!(key_name(Keycode::Q), "Q");
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        let error =
+            parse("{\"t\":0,\"event\":\"quit\"}\n{\"t\":5,\"event\":\"jump\"}").unwrap_err();
+        assert!(error.starts_with("line 2:"), "{}", error);
+        let error = parse(r#"{"t":0,"event":"key_down","key":"Hyper","shift":false}"#).unwrap_err();
+        assert!(error.contains("unknown key"), "{}", error);
+        //times that can't be played back
+        let error = parse(r#"{"t":18446744073709551615,"event":"quit"}"#).unwrap_err();
+        assert!(error.contains("is more than"), "{}", error);
+        let error =
+            parse("{\"t\":5,\"event\":\"quit\"}\n{\"t\":4,\"event\":\"quit\"}").unwrap_err();
+        assert!(error.starts_with("line 2: t 4 is earlier"), "{}", error);
+    }
+}
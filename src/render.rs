@@ -0,0 +1,116 @@
+//Choice of how the background escape times get computed
+use crate::auto_iterations;
+use crate::complex_bbox::ComplexBBox;
+use crate::kernel::{self, Kernel};
+use crate::subdivide;
+use crate::supersample::{self, AntiAlias};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    //every pixel iterated independently with the selected kernel
+    #[default]
+    BruteForce,
+    //Mariani–Silver rectangle subdivision, see subdivide.rs
+    Subdivide,
+}
+
+impl Strategy {
+    pub fn next(self) -> Strategy {
+        match self {
+            Strategy::BruteForce => Strategy::Subdivide,
+            Strategy::Subdivide => Strategy::BruteForce,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::BruteForce => "brute force",
+            Strategy::Subdivide => "subdivide",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RenderSettings {
+    pub iterations: u32,
+    pub kernel: Kernel,
+    pub strategy: Strategy,
+    //pick `iterations` from the view instead of keeping it fixed
+    pub auto_iterations: bool,
+    //supersampling for the final pass, see supersample.rs
+    pub antialias: AntiAlias,
+}
+
+impl RenderSettings {
+    pub fn new(iterations: u32) -> RenderSettings {
+        RenderSettings {
+            iterations,
+            kernel: Kernel::default(),
+            strategy: Strategy::default(),
+            auto_iterations: false,
+            antialias: AntiAlias::Off,
+        }
+    }
+}
+
+//Escape times for a `w` x `h` image of `view`.  In auto mode `base` seeds the
+//iteration limit and the chosen limit is stored back into `settings`.
+pub fn render(
+    view: &ComplexBBox,
+    w: usize,
+    h: usize,
+    settings: &mut RenderSettings,
+    base: u32,
+) -> Vec<u32> {
+    if !settings.auto_iterations {
+        let RenderSettings {
+            iterations,
+            kernel,
+            strategy,
+            ..
+        } = *settings;
+        return render_iterations(view, w, h, iterations, kernel, strategy);
+    }
+
+    let start = auto_iterations::starting_iterations(view, base);
+    let mut counts = render_iterations(view, w, h, start, settings.kernel, settings.strategy);
+    settings.iterations = auto_iterations::raise(view, w, h, &mut counts, start);
+    counts
+}
+
+//Finished background image as RGBA bytes, anti-aliased if that is selected
+pub fn render_rgba(
+    view: &ComplexBBox,
+    w: usize,
+    h: usize,
+    settings: &mut RenderSettings,
+    base: u32,
+) -> Vec<u8> {
+    let mut pixels = vec![0; w * h * 4];
+    let counts = render(view, w, h, settings, base);
+    if settings.antialias == AntiAlias::Off {
+        kernel::colorize(&counts, settings.iterations, w, &mut pixels, w * 4);
+    } else {
+        let (iter, antialias) = (settings.iterations, settings.antialias);
+        supersample::render(view, w, h, iter, antialias, &mut pixels, w * 4);
+    }
+    //ARGB8888 is stored blue first
+    for pixel in pixels.chunks_mut(4) {
+        pixel.swap(0, 2);
+    }
+    pixels
+}
+
+pub fn render_iterations(
+    view: &ComplexBBox,
+    w: usize,
+    h: usize,
+    iter: u32,
+    kernel: Kernel,
+    strategy: Strategy,
+) -> Vec<u32> {
+    match strategy {
+        Strategy::BruteForce => kernel::render_iterations(view, w, h, iter, kernel),
+        Strategy::Subdivide => subdivide::render_iterations(view, w, h, iter),
+    }
+}
@@ -0,0 +1,165 @@
+//View settings stored alongside a screenshot, so the view can be restored later
+use crate::auto_iterations::MAX_ITERATIONS;
+use crate::complex_bbox::{self, ComplexBBox};
+use crate::png_io;
+use num::Complex;
+
+pub const FORMULA: &str = "z^2+c";
+
+const BBOX_KEY: &str = "orbits bbox"; //"ll.re ll.im ur.re ur.im"
+const ITERATIONS_KEY: &str = "orbits iterations";
+const FORMULA_KEY: &str = "orbits formula";
+const SAVED_KEY: &str = "orbits saved orbits"; //"re im; re im; ..."
+
+pub struct ViewMetadata {
+    pub view: ComplexBBox,
+    pub iterations: u32,
+    pub saved_orbits: Vec<Complex<f64>>,
+}
+
+impl ViewMetadata {
+    pub fn to_text(&self) -> Vec<(String, String)> {
+        let ComplexBBox { ll, ur } = self.view;
+        let saved: Vec<String> = self
+            .saved_orbits
+            .iter()
+            .map(|c| format!("{} {}", c.re, c.im))
+            .collect();
+        vec![
+            ("Software".to_string(), "orbits".to_string()),
+            (
+                BBOX_KEY.to_string(),
+                format!("{} {} {} {}", ll.re, ll.im, ur.re, ur.im),
+            ),
+            (ITERATIONS_KEY.to_string(), self.iterations.to_string()),
+            (FORMULA_KEY.to_string(), FORMULA.to_string()),
+            (SAVED_KEY.to_string(), saved.join("; ")),
+        ]
+    }
+
+    pub fn from_text(text: &[(String, String)]) -> Result<ViewMetadata, String> {
+        let get = |key: &str| {
+            text.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+                .ok_or(format!(
+                    "no \"{}\" entry, not a screenshot from orbits?",
+                    key
+                ))
+        };
+        let numbers = |s: &str| -> Result<Vec<f64>, String> {
+            s.split_whitespace()
+                .map(|n| match n.parse::<f64>() {
+                    Ok(x) if x.is_finite() => Ok(x),
+                    Ok(_) => Err(format!("\"{}\" isn't a finite number", n)),
+                    Err(e) => Err(format!("\"{}\": {}", n, e)),
+                })
+                .collect()
+        };
+
+        let formula = get(FORMULA_KEY)?;
+        if formula != FORMULA {
+            return Err(format!("unsupported formula \"{}\"", formula));
+        }
+
+        let bbox = numbers(get(BBOX_KEY)?)?;
+        let view = match bbox[..] {
+            [ll_re, ll_im, ur_re, ur_im] => {
+                complex_bbox::bbox(Complex::new(ll_re, ll_im), Complex::new(ur_re, ur_im))?
+            }
+            _ => {
+                return Err(format!(
+                    "expected 4 numbers for the bbox, got {}",
+                    bbox.len()
+                ))
+            }
+        };
+
+        let iterations = get(ITERATIONS_KEY)?
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| format!("iterations: {}", e))?;
+        if !(1..=MAX_ITERATIONS).contains(&iterations) {
+            return Err(format!(
+                "iterations {} isn't from 1 to {}",
+                iterations, MAX_ITERATIONS
+            ));
+        }
+
+        let mut saved_orbits = Vec::new();
+        for seed in get(SAVED_KEY)?.split(';').filter(|s| !s.trim().is_empty()) {
+            match numbers(seed)?[..] {
+                [re, im] => saved_orbits.push(Complex::new(re, im)),
+                _ => return Err(format!("saved orbit \"{}\" isn't \"re im\"", seed.trim())),
+            }
+        }
+
+        Ok(ViewMetadata {
+            view,
+            iterations,
+            saved_orbits,
+        })
+    }
+}
+
+//PNG file contents for a screenshot (RGBA bytes) with its view settings
+pub fn encode(w: usize, h: usize, rgba: &[u8], metadata: &ViewMetadata) -> Result<Vec<u8>, String> {
+    png_io::encode_rgba_with_text(w, h, rgba, &metadata.to_text())
+}
+
+pub fn decode(png: &[u8]) -> Result<ViewMetadata, String> {
+    ViewMetadata::from_text(&png_io::read_text(png)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_round_trips_through_a_png() {
+        let metadata = ViewMetadata {
+            view: ComplexBBox {
+                ll: Complex::new(-0.743643887037151, 0.131825904205330),
+                ur: Complex::new(-0.743643887037141, 0.131825904205338),
+            },
+            iterations: 12800,
+            saved_orbits: vec![Complex::new(-0.75, 0.1), Complex::new(0.25, -1e-300)],
+        };
+        let png = encode(2, 2, &[0; 16], &metadata).unwrap();
+        let restored = decode(&png).unwrap();
+
+        assert_eq!(restored.view.ll, metadata.view.ll);
+        assert_eq!(restored.view.ur, metadata.view.ur);
+        assert_eq!(restored.iterations, 12800);
+        assert_eq!(restored.saved_orbits, metadata.saved_orbits);
+    }
+
+    #[test]
+    fn plain_pngs_are_rejected_with_a_reason() {
+        let png = png_io::encode_rgba(1, 1, &[0; 4]).unwrap();
+        let err = decode(&png).err().unwrap();
+        assert!(err.contains("not a screenshot"), "{}", err);
+    }
+
+    #[test]
+    fn values_out_of_range_are_rejected() {
+        let error = |bbox: &str, iterations: &str, saved: &str| {
+            let text: Vec<(String, String)> = [
+                (BBOX_KEY, bbox),
+                (ITERATIONS_KEY, iterations),
+                (FORMULA_KEY, FORMULA),
+                (SAVED_KEY, saved),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+            ViewMetadata::from_text(&text).err().unwrap()
+        };
+        assert!(error("-2 -1 1 1", "0", "").contains("from 1 to"));
+        assert!(error("-2 -1 1 1", "4000000000", "").contains("from 1 to"));
+        assert!(error("-2 -1 NaN 1", "100", "").contains("finite"));
+        assert!(error("-2 -1 1 inf", "100", "").contains("finite"));
+        assert!(error("1 1 -2 -1", "100", "").contains("below and left"));
+        assert!(error("-2 -1 1 1", "100", "0 0; -inf 0").contains("finite"));
+    }
+}
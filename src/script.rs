@@ -0,0 +1,401 @@
+//Rhai scripts driving the viewer, from the console or --script, e.g.
+//
+//    for i in 0..12 {
+//        let t = 2.0 * PI() * i / 12.0;
+//        save_orbit(-0.75 + 0.1 * cos(t), 0.1 * sin(t));
+//        print(screenshot());
+//    }
+//
+//Functions, numbers may be whole or not:
+//    state()                       #{view: #{ll: [re, im], ur: [re, im]}, iterations, saved_orbits}
+//    center_on(re, im, zoom)       center the view, zoom 1 is the initial 2 by 2 view
+//    set_view(ll_re, ll_im, ur_re, ur_im)
+//    set_iterations(n)
+//    save_orbit(re, im)            returns how many are saved
+//    clear_orbits()
+//    orbit(re, im)                 the iterates [re, im] until the orbit escapes
+//    screenshot(), screenshot(hud) returns the path once it's written
+//    export_orbits(re, im, "csv" or "json"), export_svg(re, im, axes),
+//    export_location()
+//    zoom_sequence(re, im, with_orbit), or with frames and depth after, 120 and
+//                                  1000 times magnified by the end if left out
+//    action(name)                  anything a key can do, by its [keys] name
+//Scripts run one after another on their own thread, keeping their variables,
+//and send their commands to the main loop like the control socket does.
+//Escape in the console cancels the running script and any waiting.
+use num::Complex;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, INT};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use orbits::action::Action;
+use orbits::app::Task;
+use orbits::auto_iterations::MAX_ITERATIONS;
+use orbits::zoom_sequence;
+
+use crate::cli;
+use crate::control::{self, Command, Request, Wake};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Clone)]
+struct Link {
+    requests: Sender<Request>,
+    wake: Wake,
+}
+
+impl Link {
+    fn call(&self, command: Command) -> ScriptResult<Value> {
+        match control::call(&self.requests, &*self.wake, command) {
+            Some(result) => result.map_err(|e| e.into()),
+            None => Err("the viewer has closed".into()),
+        }
+    }
+
+    fn zoom_sequence(
+        &self,
+        c: Complex<f64>,
+        with_orbit: bool,
+        frames: usize,
+        depth: f64,
+    ) -> ScriptResult<()> {
+        let task = Task::ZoomSequence {
+            c,
+            with_orbit,
+            frames,
+            depth,
+        };
+        self.call(Command::Run(task)).map(|_| ())
+    }
+}
+
+fn number(value: &Dynamic) -> ScriptResult<f64> {
+    match value.as_float() {
+        Ok(x) => Ok(x),
+        Err(_) => value
+            .as_int()
+            .map(|n| n as f64)
+            .map_err(|t| format!("expected a number, not {}", t).into()),
+    }
+}
+
+fn complex(re: &Dynamic, im: &Dynamic) -> ScriptResult<Complex<f64>> {
+    let c = Complex::new(number(re)?, number(im)?);
+    if c.re.is_finite() && c.im.is_finite() {
+        Ok(c)
+    } else {
+        Err(format!("{} isn't a finite number", c).into())
+    }
+}
+
+fn to_dynamic(value: &Value) -> Dynamic {
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => n.into(),
+            None => n.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        Value::String(s) => s.clone().into(),
+        Value::Array(values) => values.iter().map(to_dynamic).collect::<Array>().into(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(name, v)| (name.as_str().into(), to_dynamic(v)))
+            .collect::<Map>()
+            .into(),
+    }
+}
+
+fn engine(link: Link) -> Engine {
+    let mut engine = Engine::new();
+    let l = link.clone();
+    engine.on_print(move |text| {
+        let _ = l.call(Command::Print(text.to_string()));
+    });
+
+    let l = link.clone();
+    engine.register_fn("state", move || -> ScriptResult<Dynamic> {
+        Ok(to_dynamic(&l.call(Command::GetState)?))
+    });
+    let l = link.clone();
+    engine.register_fn(
+        "center_on",
+        move |re: Dynamic, im: Dynamic, zoom: Dynamic| -> ScriptResult<()> {
+            let zoom = number(&zoom)?;
+            if !(zoom.is_finite() && zoom > 0.0) {
+                return Err("zoom must be a positive number".into());
+            }
+            let view = cli::centered(Some(complex(&re, &im)?), Some(zoom));
+            l.call(Command::SetView(view)).map(|_| ())
+        },
+    );
+    let l = link.clone();
+    engine.register_fn(
+        "set_view",
+        move |ll_re: Dynamic, ll_im: Dynamic, ur_re: Dynamic, ur_im: Dynamic| -> ScriptResult<()> {
+            let view = cli::bbox(complex(&ll_re, &ll_im)?, complex(&ur_re, &ur_im)?)?;
+            l.call(Command::SetView(view)).map(|_| ())
+        },
+    );
+    let l = link.clone();
+    engine.register_fn("set_iterations", move |n: INT| -> ScriptResult<()> {
+        if !(1..=MAX_ITERATIONS as INT).contains(&n) {
+            return Err(format!("iterations must be from 1 to {}", MAX_ITERATIONS).into());
+        }
+        l.call(Command::SetIterations(n as u32)).map(|_| ())
+    });
+    let l = link.clone();
+    engine.register_fn(
+        "save_orbit",
+        move |re: Dynamic, im: Dynamic| -> ScriptResult<Dynamic> {
+            let saved = l.call(Command::SaveOrbit(complex(&re, &im)?))?;
+            Ok(to_dynamic(&saved["saved"]))
+        },
+    );
+    let l = link.clone();
+    engine.register_fn("clear_orbits", move || -> ScriptResult<()> {
+        l.call(Command::Action(Action::ClearOrbits)).map(|_| ())
+    });
+    let l = link.clone();
+    engine.register_fn(
+        "orbit",
+        move |re: Dynamic, im: Dynamic| -> ScriptResult<Dynamic> {
+            let orbit = l.call(Command::GetOrbit(complex(&re, &im)?))?;
+            Ok(to_dynamic(&orbit["orbit"]))
+        },
+    );
+    let screenshot = |link: &Link, hud| -> ScriptResult<String> {
+        let taken = link.call(Command::Screenshot { hud })?;
+        Ok(taken["path"].as_str().unwrap_or_default().to_string())
+    };
+    let l = link.clone();
+    engine.register_fn("screenshot", move || screenshot(&l, true));
+    let l = link.clone();
+    engine.register_fn("screenshot", move |hud: bool| screenshot(&l, hud));
+
+    let l = link.clone();
+    engine.register_fn(
+        "export_orbits",
+        move |re: Dynamic, im: Dynamic, format: &str| -> ScriptResult<()> {
+            let json = match format {
+                "csv" => false,
+                "json" => true,
+                _ => return Err(format!("unknown format \"{}\", use csv or json", format).into()),
+            };
+            let c = complex(&re, &im)?;
+            l.call(Command::Run(Task::ExportOrbits { c, json }))
+                .map(|_| ())
+        },
+    );
+    let l = link.clone();
+    engine.register_fn(
+        "export_svg",
+        move |re: Dynamic, im: Dynamic, axes: bool| -> ScriptResult<()> {
+            let c = complex(&re, &im)?;
+            l.call(Command::Run(Task::ExportSvg { c, axes }))
+                .map(|_| ())
+        },
+    );
+    let l = link.clone();
+    engine.register_fn("export_location", move || -> ScriptResult<()> {
+        l.call(Command::Run(Task::ExportLocation)).map(|_| ())
+    });
+    let l = link.clone();
+    engine.register_fn(
+        "zoom_sequence",
+        move |re: Dynamic, im: Dynamic, with_orbit: bool| -> ScriptResult<()> {
+            let c = complex(&re, &im)?;
+            l.zoom_sequence(c, with_orbit, zoom_sequence::FRAMES, zoom_sequence::DEPTH)
+        },
+    );
+    let l = link.clone();
+    engine.register_fn(
+        "zoom_sequence",
+        move |re: Dynamic,
+              im: Dynamic,
+              with_orbit: bool,
+              frames: INT,
+              depth: Dynamic|
+              -> ScriptResult<()> {
+            let c = complex(&re, &im)?;
+            if !(1..=zoom_sequence::MAX_FRAMES as INT).contains(&frames) {
+                return Err(
+                    format!("frames must be from 1 to {}", zoom_sequence::MAX_FRAMES).into(),
+                );
+            }
+            let depth = number(&depth)?;
+            if !(depth.is_finite() && depth > 0.0) {
+                return Err("depth must be a positive number".into());
+            }
+            l.zoom_sequence(c, with_orbit, frames as usize, depth)
+        },
+    );
+    let l = link;
+    engine.register_fn("action", move |name: &str| -> ScriptResult<()> {
+        let action = Action::from_name(name)?;
+        l.call(Command::Action(action)).map(|_| ())
+    });
+    engine
+}
+
+pub struct Scripts {
+    sources: Sender<(usize, String)>, //numbered in the order they're run
+    submitted: AtomicUsize,
+    cancelled: Arc<AtomicUsize>, //scripts numbered below this stop
+}
+
+impl Scripts {
+    //The thread scripts run on, its commands go to `requests`.  Each script
+    //ends with Command::ScriptDone.
+    pub fn start(requests: Sender<Request>, wake: Wake) -> Scripts {
+        let (sources, receiver) = mpsc::channel::<(usize, String)>();
+        let cancelled = Arc::new(AtomicUsize::new(0));
+        let stop = cancelled.clone();
+        thread::spawn(move || {
+            let running = Arc::new(AtomicUsize::new(0));
+            let (r, c) = (running.clone(), stop.clone());
+            let link = Link { requests, wake };
+            let mut engine = engine(link.clone());
+            //checked every operation, so loops that never end can be stopped
+            engine.on_progress(move |_| {
+                if r.load(Ordering::Relaxed) < c.load(Ordering::Relaxed) {
+                    Some(Dynamic::UNIT)
+                } else {
+                    None
+                }
+            });
+            let mut scope = Scope::new();
+            for (n, source) in receiver {
+                running.store(n, Ordering::Relaxed);
+                let result = if n < stop.load(Ordering::Relaxed) {
+                    Err("script cancelled".to_string())
+                } else {
+                    engine
+                        .run_with_scope(&mut scope, &source)
+                        .map_err(|e| match *e {
+                            EvalAltResult::ErrorTerminated(..) => "script cancelled".to_string(),
+                            e => e.to_string(),
+                        })
+                };
+                if link.call(Command::ScriptDone(result)).is_err() {
+                    break;
+                }
+            }
+        });
+        Scripts {
+            sources,
+            submitted: AtomicUsize::new(0),
+            cancelled,
+        }
+    }
+
+    pub fn run(&self, source: String) {
+        let n = self.submitted.fetch_add(1, Ordering::Relaxed);
+        let _ = self.sources.send((n, source));
+    }
+
+    //Stop the running script and drop the ones waiting their turn
+    pub fn cancel(&self) {
+        let submitted = self.submitted.load(Ordering::Relaxed);
+        self.cancelled.store(submitted, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orbits::app::{App, Effects};
+    use serde_json::json;
+    use std::sync::mpsc::Receiver;
+    use std::sync::Arc;
+
+    //Stand in for the main loop until the script is done, returning how it
+    //went and the tasks it left
+    fn serve(app: &mut App, requests: &Receiver<Request>) -> (Result<(), String>, Vec<Task>) {
+        let mut tasks = Vec::new();
+        loop {
+            let request = requests.recv().unwrap();
+            let done = match &request.command {
+                Command::ScriptDone(result) => Some(result.clone()),
+                _ => None,
+            };
+            let screenshot = matches!(request.command, Command::Screenshot { .. });
+            let (mut result, effects): (Value, Effects) = control::execute(app, request.command);
+            if screenshot {
+                result = json!({ "path": format!("shot{}.png", tasks.len()) });
+            }
+            tasks.extend(effects.task);
+            let _ = request.reply.send(Ok(result));
+            if let Some(done) = done {
+                return (done, tasks);
+            }
+        }
+    }
+
+    #[test]
+    fn scripts_save_orbits_and_take_screenshots() {
+        let (sender, requests) = mpsc::channel();
+        let scripts = Scripts::start(sender, Arc::new(|| {}));
+        let mut app = App::new(cli::centered(None, None), 50, (200, 200));
+
+        scripts.run(
+            r#"
+            center_on(-0.75, 0, 4);
+            set_iterations(200);
+            for i in 0..4 {
+                let t = PI() * i / 2.0;
+                save_orbit(-0.75 + 0.1 * cos(t), 0.1 * sin(t));
+                print(screenshot(false));
+            }
+            let n = orbit(-1, 0).len();
+            export_orbits(0, 0, "json");
+            "#
+            .to_string(),
+        );
+        let (result, tasks) = serve(&mut app, &requests);
+        assert_eq!(result, Ok(()));
+        assert_eq!(app.saved_orbits.len(), 4);
+        assert!((app.saved_orbits[1] - Complex::new(-0.75, 0.1)).norm() < 1e-12);
+        assert_eq!(app.settings.iterations, 200);
+        assert_eq!(app.view.ur - app.view.ll, Complex::new(0.5, 0.5));
+        assert_eq!(tasks.len(), 5);
+        assert_eq!(
+            tasks[4],
+            Task::ExportOrbits {
+                c: Complex::new(0.0, 0.0),
+                json: true
+            }
+        );
+        let output: Vec<&String> = app.console.last_lines(4).collect();
+        assert_eq!(output, ["shot0.png", "shot1.png", "shot2.png", "shot3.png"]);
+
+        //variables carry over to the next script, errors end it
+        scripts.run("print(n); print(state().saved_orbits.len()); set_iterations(0);".to_string());
+        let (result, _) = serve(&mut app, &requests);
+        assert!(result.unwrap_err().contains("iterations must be from 1"));
+        let output: Vec<&String> = app.console.last_lines(3).collect();
+        assert_eq!(output[..2], ["51", "4"]); //z0 to z50, it never escapes
+    }
+
+    #[test]
+    fn cancelling_stops_the_running_script_and_those_waiting() {
+        let (sender, requests) = mpsc::channel();
+        let scripts = Scripts::start(sender, Arc::new(|| {}));
+        let mut app = App::new(cli::centered(None, None), 50, (200, 200));
+
+        scripts.run("while true {}".to_string());
+        scripts.run("save_orbit(0, 0);".to_string());
+        scripts.cancel();
+        let cancelled = Err("script cancelled".to_string());
+        assert_eq!(serve(&mut app, &requests).0, cancelled);
+        assert_eq!(serve(&mut app, &requests).0, cancelled);
+        assert!(app.saved_orbits.is_empty());
+
+        scripts.run("save_orbit(0, 0);".to_string());
+        assert_eq!(serve(&mut app, &requests).0, Ok(()));
+        assert_eq!(app.saved_orbits.len(), 1);
+    }
+}
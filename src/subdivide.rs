@@ -0,0 +1,179 @@
+//Mariani–Silver subdivision: evaluate the border of a rectangle, and if every
+//border pixel has the same escape time, fill the inside without iterating it.
+//Otherwise split into quarters and try again, down to small blocks which are
+//evaluated pixel by pixel.
+use crate::complex_bbox::ComplexBBox;
+use crate::kernel::escape_time;
+use rayon::prelude::*;
+use std::convert::TryInto;
+
+const TILE_SIZE: usize = 64; //top level blocks, rendered in parallel
+const MIN_BLOCK: usize = 6; //blocks this size or smaller are not subdivided
+const UNKNOWN: u32 = u32::MAX;
+
+struct Tile<'a> {
+    view: &'a ComplexBBox,
+    iter: u32,
+    w: i32,
+    h: i32,
+    x0: usize,
+    y0: usize,
+    tile_w: usize,
+    counts: Vec<u32>,
+}
+
+impl<'a> Tile<'a> {
+    //escape time at tile coordinates (x, y), computed at most once
+    fn at(&mut self, x: usize, y: usize) -> u32 {
+        let idx = y * self.tile_w + x;
+        if self.counts[idx] == UNKNOWN {
+            let c = self.view.screen_to_complex(
+                (self.x0 + x) as i32,
+                (self.y0 + y) as i32,
+                self.w,
+                self.h,
+            );
+            self.counts[idx] = escape_time(c, self.iter);
+        }
+        self.counts[idx]
+    }
+
+    //rectangle from (x0, y0) to (x1, y1) inclusive
+    fn subdivide(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        if x1 - x0 < MIN_BLOCK || y1 - y0 < MIN_BLOCK {
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    self.at(x, y);
+                }
+            }
+            return;
+        }
+
+        let first = self.at(x0, y0);
+        let mut uniform = true;
+        for x in x0..=x1 {
+            uniform &= self.at(x, y0) == first;
+            uniform &= self.at(x, y1) == first;
+        }
+        for y in y0..=y1 {
+            uniform &= self.at(x0, y) == first;
+            uniform &= self.at(x1, y) == first;
+        }
+
+        if uniform {
+            for y in y0 + 1..y1 {
+                let row = y * self.tile_w;
+                self.counts[row + x0 + 1..row + x1].fill(first);
+            }
+        } else {
+            let (mx, my) = ((x0 + x1) / 2, (y0 + y1) / 2);
+            self.subdivide(x0, y0, mx, my);
+            self.subdivide(mx, y0, x1, my);
+            self.subdivide(x0, my, mx, y1);
+            self.subdivide(mx, my, x1, y1);
+        }
+    }
+}
+
+//Escape times for every pixel of a `w` x `h` image of `view`, row-major.
+//Produces the same output as kernel::render_iterations wherever the
+//filled regions really are uniform.
+pub fn render_iterations(view: &ComplexBBox, w: usize, h: usize, iter: u32) -> Vec<u32> {
+    let mut counts = vec![0; w * h];
+    if w == 0 || h == 0 {
+        return counts;
+    }
+    let (wi, hi): (i32, i32) = (w.try_into().unwrap(), h.try_into().unwrap());
+
+    let tiles: Vec<(usize, usize)> = (0..h)
+        .step_by(TILE_SIZE)
+        .flat_map(|y0| (0..w).step_by(TILE_SIZE).map(move |x0| (x0, y0)))
+        .collect();
+
+    let rendered: Vec<Tile> = tiles
+        .into_par_iter()
+        .map(|(x0, y0)| {
+            let tile_w = TILE_SIZE.min(w - x0);
+            let tile_h = TILE_SIZE.min(h - y0);
+            let mut tile = Tile {
+                view,
+                iter,
+                w: wi,
+                h: hi,
+                x0,
+                y0,
+                tile_w,
+                counts: vec![UNKNOWN; tile_w * tile_h],
+            };
+            tile.subdivide(0, 0, tile_w - 1, tile_h - 1);
+            tile
+        })
+        .collect();
+
+    for tile in rendered {
+        for (ty, row) in tile.counts.chunks(tile.tile_w).enumerate() {
+            let start = (tile.y0 + ty) * w + tile.x0;
+            counts[start..start + tile.tile_w].copy_from_slice(row);
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::{self, Kernel};
+    use num::Complex;
+
+    fn assert_agrees(view: ComplexBBox, w: usize, h: usize, iter: u32) {
+        let brute = kernel::render_iterations(&view, w, h, iter, Kernel::Scalar);
+        let subdivided = render_iterations(&view, w, h, iter);
+        let mismatches = brute
+            .iter()
+            .zip(subdivided.iter())
+            .filter(|(a, b)| a != b)
+            .count();
+        assert_eq!(mismatches, 0, "{} of {} pixels differ", mismatches, w * h);
+    }
+
+    #[test]
+    fn full_set_matches_brute_force() {
+        let j = Complex { re: 0.0, im: 1.0 };
+        let view = ComplexBBox {
+            ll: -1.5 - j,
+            ur: 0.5 + j,
+        };
+        assert_agrees(view, 800, 600, 50);
+        assert_agrees(view, 317, 211, 200);
+    }
+
+    #[test]
+    fn seahorse_valley_matches_brute_force() {
+        let view = ComplexBBox {
+            ll: Complex::new(-0.7665, 0.0900),
+            ur: Complex::new(-0.7265, 0.1200),
+        };
+        assert_agrees(view, 400, 300, 300);
+    }
+
+    #[test]
+    fn main_cardioid_interior_matches_brute_force() {
+        let view = ComplexBBox {
+            ll: Complex::new(-0.5, -0.3),
+            ur: Complex::new(0.1, 0.15),
+        };
+        assert_agrees(view, 200, 150, 1000);
+    }
+
+    #[test]
+    fn odd_sizes_are_fully_covered() {
+        let j = Complex { re: 0.0, im: 1.0 };
+        let view = ComplexBBox {
+            ll: -1.5 - j,
+            ur: 0.5 + j,
+        };
+        for (w, h) in [(1, 1), (1, 70), (70, 1), (65, 129), (7, 7)] {
+            assert_agrees(view, w, h, 50);
+        }
+    }
+}
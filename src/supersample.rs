@@ -0,0 +1,202 @@
+//Supersampled anti-aliasing for the final background pass.  Every pixel gets
+//n x n samples, either in the middle of the cells of a regular grid or at a
+//random spot within each cell, and the sample colors are averaged in linear
+//light before being encoded back to sRGB.  The samples are centered on the
+//point the preview pass uses for the pixel, so refining doesn't shift the image.
+use crate::complex_bbox::ComplexBBox;
+use crate::kernel::{escape_time_lanes, palette};
+use num::Complex;
+use rayon::prelude::*;
+use std::convert::TryInto;
+
+const LANES: usize = 4;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AntiAlias {
+    #[default]
+    Off,
+    Grid(u32),
+    Jittered(u32),
+}
+
+impl AntiAlias {
+    pub fn next(self) -> AntiAlias {
+        match self {
+            AntiAlias::Off => AntiAlias::Grid(2),
+            AntiAlias::Grid(2) => AntiAlias::Grid(3),
+            AntiAlias::Grid(_) => AntiAlias::Jittered(4),
+            AntiAlias::Jittered(_) => AntiAlias::Off,
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            AntiAlias::Off => "off".to_string(),
+            AntiAlias::Grid(n) => format!("{}x{} grid", n, n),
+            AntiAlias::Jittered(n) => format!("{}x{} jittered", n, n),
+        }
+    }
+
+    //(x, y) sample positions for pixel (px, py), as offsets from the pixel's
+    //preview sample point in units of pixels
+    fn offsets(self, px: usize, py: usize) -> Vec<(f64, f64)> {
+        let n = match self {
+            AntiAlias::Off => return vec![(0.0, 0.0)],
+            AntiAlias::Grid(n) | AntiAlias::Jittered(n) => n.max(1),
+        };
+        let mut offsets = Vec::with_capacity((n * n) as usize);
+        for sy in 0..n {
+            for sx in 0..n {
+                let (jx, jy) = match self {
+                    AntiAlias::Jittered(_) => jitter(px, py, (sy * n + sx) as usize),
+                    _ => (0.5, 0.5),
+                };
+                offsets.push((
+                    (sx as f64 + jx) / n as f64 - 0.5,
+                    (sy as f64 + jy) / n as f64 - 0.5,
+                ));
+            }
+        }
+        offsets
+    }
+}
+
+//Deterministic pseudo random point in [0,1) x [0,1), so re-rendering the same
+//view gives the same image
+fn jitter(x: usize, y: usize, sample: usize) -> (f64, f64) {
+    let mut h = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (sample as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    let mut next = || {
+        //splitmix64
+        h = h.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = h;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        ((z ^ (z >> 31)) >> 11) as f64 / (1u64 << 53) as f64
+    };
+    (next(), next())
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 255.0).round() as u8
+}
+
+//Render a supersampled `w` x `h` image of `view` straight into a
+//4-bytes-per-pixel ARGB8888 buffer
+pub fn render(
+    view: &ComplexBBox,
+    w: usize,
+    h: usize,
+    iter: u32,
+    antialias: AntiAlias,
+    pixel_buffer: &mut [u8],
+    pitch: usize,
+) {
+    let (wi, hi): (i32, i32) = (w.try_into().unwrap(), h.try_into().unwrap());
+    let pixel = view.complex_deltas(wi, hi, 1, 1);
+
+    pixel_buffer
+        .par_chunks_mut(pitch)
+        .take(h)
+        .enumerate()
+        .for_each(|(y, buffer)| {
+            for x in 0..w {
+                let corner = view.screen_to_complex(x as i32, y as i32, wi, hi);
+                let samples: Vec<Complex<f64>> = antialias
+                    .offsets(x, y)
+                    .into_iter()
+                    .map(|(ox, oy)| corner + Complex::new(ox * pixel.re, oy * pixel.im))
+                    .collect();
+
+                let mut sum = [0.0_f32; 3];
+                for chunk in samples.chunks(LANES) {
+                    let mut cre = [0.0; LANES];
+                    let mut cim = [0.0; LANES];
+                    for l in 0..LANES {
+                        let c = chunk[l.min(chunk.len() - 1)];
+                        cre[l] = c.re;
+                        cim[l] = c.im;
+                    }
+                    let counts = escape_time_lanes::<LANES>(&cre, &cim, iter);
+                    for count in &counts[..chunk.len()] {
+                        let (r, g, b) = palette(*count, iter);
+                        sum[0] += srgb_to_linear(r);
+                        sum[1] += srgb_to_linear(g);
+                        sum[2] += srgb_to_linear(b);
+                    }
+                }
+
+                let n = samples.len() as f32;
+                let offset: usize = x * 4;
+                buffer[offset] = linear_to_srgb(sum[2] / n); //Blue
+                buffer[offset + 1] = linear_to_srgb(sum[1] / n); //Green
+                buffer[offset + 2] = linear_to_srgb(sum[0] / n); //Red
+                buffer[offset + 3] = 255; //Alpha
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::{self, Kernel};
+
+    fn home() -> ComplexBBox {
+        let j = Complex { re: 0.0, im: 1.0 };
+        ComplexBBox {
+            ll: -1.5 - j,
+            ur: 0.5 + j,
+        }
+    }
+
+    #[test]
+    fn single_sample_matches_the_preview() {
+        let (w, h, iter) = (64, 48, 50);
+        let counts = kernel::render_iterations(&home(), w, h, iter, Kernel::Scalar);
+        let mut preview = vec![0; w * h * 4];
+        kernel::colorize(&counts, iter, w, &mut preview, w * 4);
+
+        for antialias in [AntiAlias::Off, AntiAlias::Grid(1)] {
+            let mut refined = vec![0; w * h * 4];
+            render(&home(), w, h, iter, antialias, &mut refined, w * 4);
+            assert_eq!(preview, refined);
+        }
+    }
+
+    #[test]
+    fn samples_are_averaged_in_linear_light() {
+        assert_eq!(linear_to_srgb(srgb_to_linear(0)), 0);
+        assert_eq!(linear_to_srgb(srgb_to_linear(255)), 255);
+        //half black and half white is much lighter than 128 once gamma is accounted for
+        let half = (srgb_to_linear(0) + srgb_to_linear(255)) / 2.0;
+        assert_eq!(linear_to_srgb(half), 188);
+    }
+
+    #[test]
+    fn jittered_samples_stay_inside_the_pixel() {
+        for (x, y) in [(0, 0), (17, 3), (799, 599)] {
+            let offsets = AntiAlias::Jittered(4).offsets(x, y);
+            assert_eq!(offsets.len(), 16);
+            assert!(offsets
+                .iter()
+                .all(|(ox, oy)| (-0.5..0.5).contains(ox) && (-0.5..0.5).contains(oy)));
+            assert_eq!(offsets, AntiAlias::Jittered(4).offsets(x, y));
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//Resolution independent figures: the background is embedded as a PNG and the
+//orbits, axes and label are drawn on top as vectors
+use crate::complex_bbox::ComplexBBox;
+use crate::png_io;
+use base64::Engine;
+use num::Complex;
+use std::convert::TryInto;
+use std::fmt::Write;
+
+const TICKS: f64 = 5.0; //roughly how many labelled ticks along each edge
+
+pub struct SvgOrbit<'a> {
+    pub points: &'a [Complex<f64>],
+    pub first_color: &'a str, //of the first segment, like the on-screen orbits
+    pub color: &'a str,
+}
+
+#[derive(Default)]
+pub struct SvgOptions {
+    pub axes: bool,
+    pub label: Option<String>, //text for the bottom left corner
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+//1, 2 or 5 times a power of ten, about `span / TICKS`
+fn tick_step(span: f64) -> f64 {
+    let rough = span.abs() / TICKS;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude)
+}
+
+//enough decimals to tell ticks `step` apart
+fn tick_label(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+fn ticks(from: f64, to: f64) -> Vec<f64> {
+    let (lo, hi) = (from.min(to), from.max(to));
+    let step = tick_step(hi - lo);
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+fn axes(svg: &mut String, view: &ComplexBBox, w: i32, h: i32) {
+    let origin = view.complex_to_screen(Complex::new(0.0, 0.0), w, h);
+    writeln!(
+        svg,
+        r##"<g stroke="#808080" stroke-width="1" fill="none">"##
+    )
+    .unwrap();
+    if (0..w).contains(&origin.x()) {
+        writeln!(
+            svg,
+            r#"<line x1="{x}" y1="0" x2="{x}" y2="{}"/>"#,
+            h,
+            x = origin.x()
+        )
+        .unwrap();
+    }
+    if (0..h).contains(&origin.y()) {
+        writeln!(
+            svg,
+            r#"<line x1="0" y1="{y}" x2="{}" y2="{y}"/>"#,
+            w,
+            y = origin.y()
+        )
+        .unwrap();
+    }
+    writeln!(svg, "</g>").unwrap();
+
+    //ticks along the bottom (real part) and left (imaginary part) edges
+    writeln!(
+        svg,
+        r##"<g stroke="#404040" fill="#404040" font-family="monospace" font-size="10">"##
+    )
+    .unwrap();
+    let re_step = tick_step(view.ur.re - view.ll.re);
+    for re in ticks(view.ll.re, view.ur.re) {
+        let x = view
+            .complex_to_screen(Complex::new(re, view.ll.im), w, h)
+            .x();
+        writeln!(
+            svg,
+            r#"<line x1="{x}" y1="{}" x2="{x}" y2="{}"/><text x="{x}" y="{}" text-anchor="middle" stroke="none">{}</text>"#,
+            h,
+            h - 6,
+            h - 9,
+            tick_label(re, re_step),
+            x = x
+        )
+        .unwrap();
+    }
+    let im_step = tick_step(view.ur.im - view.ll.im);
+    for im in ticks(view.ll.im, view.ur.im) {
+        let y = view
+            .complex_to_screen(Complex::new(view.ll.re, im), w, h)
+            .y();
+        writeln!(
+            svg,
+            r#"<line x1="0" y1="{y}" x2="6" y2="{y}"/><text x="9" y="{}" stroke="none">{}i</text>"#,
+            y + 3,
+            tick_label(im, im_step),
+            y = y
+        )
+        .unwrap();
+    }
+    writeln!(svg, "</g>").unwrap();
+}
+
+//SVG document for a `w` x `h` view with `background` (RGBA bytes) behind it
+pub fn view_svg(
+    view: &ComplexBBox,
+    w: usize,
+    h: usize,
+    background: &[u8],
+    orbits: &[SvgOrbit],
+    options: &SvgOptions,
+) -> Result<String, String> {
+    let png = png_io::encode_rgba(w, h, background)?;
+    let (wi, hi): (i32, i32) = (w.try_into().unwrap(), h.try_into().unwrap());
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = w,
+        h = h
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<image x="0" y="0" width="{}" height="{}" xlink:href="data:image/png;base64,{}"/>"#,
+        w,
+        h,
+        base64::engine::general_purpose::STANDARD.encode(png)
+    )
+    .unwrap();
+
+    if options.axes {
+        axes(&mut svg, view, wi, hi);
+    }
+
+    writeln!(
+        svg,
+        r#"<g fill="none" stroke-width="1" stroke-linejoin="round">"#
+    )
+    .unwrap();
+    for orbit in orbits {
+        let screen: Vec<_> = orbit
+            .points
+            .iter()
+            .map(|z| view.complex_to_screen(*z, wi, hi))
+            .collect();
+        if let [p1, p2, ..] = screen[..] {
+            writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#,
+                p1.x(),
+                p1.y(),
+                p2.x(),
+                p2.y(),
+                escape(orbit.first_color)
+            )
+            .unwrap();
+        }
+        if screen.len() > 2 {
+            let points: Vec<String> = screen[1..]
+                .iter()
+                .map(|p| format!("{},{}", p.x(), p.y()))
+                .collect();
+            writeln!(
+                svg,
+                r#"<polyline points="{}" stroke="{}"/>"#,
+                points.join(" "),
+                escape(orbit.color)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(svg, "</g>").unwrap();
+
+    if let Some(label) = &options.label {
+        //same look as the on-screen coordinate display
+        let width = label.chars().count() * 7 + 6;
+        writeln!(
+            svg,
+            r##"<rect x="5" y="{}" width="{}" height="16" fill="#c8c8c8"/><text x="8" y="{}" font-family="monospace" font-size="12" fill="#7d007d">{}</text>"##,
+            hi - 21,
+            width,
+            hi - 9,
+            escape(label)
+        )
+        .unwrap();
+    }
+
+    writeln!(svg, "</svg>").unwrap();
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_land_on_round_numbers() {
+        assert_eq!(ticks(-1.5, 0.5), vec![-1.5, -1.0, -0.5, 0.0, 0.5]);
+        assert_eq!(ticks(-1.0, 1.0), vec![-1.0, -0.5, 0.0, 0.5, 1.0]);
+        assert!((tick_step(0.003) - 0.001).abs() < 1e-15);
+        assert_eq!(tick_label(0.1 + 0.2, 0.1), "0.3");
+        assert_eq!(tick_label(-0.7436, 0.0002), "-0.7436");
+    }
+
+    #[test]
+    fn orbits_are_vector_polylines_over_the_embedded_background() {
+        let j = Complex { re: 0.0, im: 1.0 };
+        let view = ComplexBBox {
+            ll: -1.5 - j,
+            ur: 0.5 + j,
+        };
+        let (w, h) = (8, 8);
+        let background = vec![255; w * h * 4];
+        let points = [Complex::new(0.0, 0.0), -0.5 + 0.5 * j, -1.0 + 0.0 * j];
+        let orbit = SvgOrbit {
+            points: &points,
+            first_color: "red",
+            color: "green",
+        };
+        let options = SvgOptions {
+            axes: true,
+            label: Some("-0.5 <+0.5i>".to_string()),
+        };
+        let svg = view_svg(&view, w, h, &background, &[orbit], &options).unwrap();
+
+        assert!(svg.contains("data:image/png;base64,iVBORw0KGgo"));
+        assert!(svg.contains(r#"<line x1="6" y1="4" x2="4" y2="2" stroke="red"/>"#));
+        assert!(svg.contains(r#"<polyline points="4,2 2,4" stroke="green"/>"#));
+        assert!(svg.contains("-0.5 &lt;+0.5i&gt;"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
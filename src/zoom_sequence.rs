@@ -0,0 +1,236 @@
+//Numbered PNG frames of a zoom from one view to another, for assembling into
+//a video with other tools (e.g. ffmpeg -i frame-%05d.png zoom.mp4)
+use crate::complex_bbox::ComplexBBox;
+use crate::orbit::{calc_orbits, Bailout};
+use crate::render::{self, RenderSettings};
+use crate::screenshot::{self, ViewMetadata};
+use num::Complex;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+
+const FIRST_COLOR: [u8; 4] = [255, 0, 0, 255]; //orbit colors, same as on screen
+const COLOR: [u8; 4] = [0, 255, 0, 255];
+
+pub const FRAMES: usize = 120; //unless the Z key prompt, --zoom-frames or a script says
+pub const DEPTH: f64 = 1000.0; //magnification of the last frame over the first
+pub const MAX_FRAMES: usize = 100_000;
+
+//`view` magnified `depth` times about `c`, where a sequence ends up
+pub fn target(view: &ComplexBBox, c: Complex<f64>, depth: f64) -> ComplexBBox {
+    let half = (view.ur - view.ll) / (2.0 * depth);
+    ComplexBBox {
+        ll: c - half,
+        ur: c + half,
+    }
+}
+
+//View `t` of the way (0 to 1) from `start` to `target`.  The size changes
+//exponentially, so every frame zooms by the same factor, and the center
+//moves so the zoom stays fixed on a single point instead of drifting.
+pub fn interpolate(start: &ComplexBBox, target: &ComplexBBox, t: f64) -> ComplexBBox {
+    let size = |b: &ComplexBBox| b.ur - b.ll;
+    let center = |b: &ComplexBBox| (b.ll + b.ur) / 2.0;
+    let (s0, s1) = (size(start), size(target));
+    let (c0, c1) = (center(start), center(target));
+
+    let re = s0.re * (s1.re / s0.re).powf(t);
+    let im = s0.im * (s1.im / s0.im).powf(t);
+    let k = s1.re / s0.re;
+    let c = if (1.0 - k).abs() < 1e-12 {
+        c0 + (c1 - c0) * t //same size, just pan
+    } else {
+        //fixed point of the zoom, and its distance from the center shrinking with the view
+        let fixed = (c1 - c0 * k) / (1.0 - k);
+        fixed + (c0 - fixed) * (re / s0.re)
+    };
+    let half = Complex::new(re, im) / 2.0;
+
+    ComplexBBox {
+        ll: c - half,
+        ur: c + half,
+    }
+}
+
+//`n` views from `start` to `target`, both included
+pub fn frames(start: &ComplexBBox, target: &ComplexBBox, n: usize) -> Vec<ComplexBBox> {
+    match n {
+        0 => vec![],
+        1 => vec![*target],
+        _ => (0..n)
+            .map(|i| interpolate(start, target, i as f64 / (n - 1) as f64))
+            .collect(),
+    }
+}
+
+//Clip the segment to [0, w) x [0, h) (Liang–Barsky), orbits of deep zooms
+//are mostly off screen
+fn clip(p1: (f64, f64), p2: (f64, f64), w: f64, h: f64) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (p2.0 - p1.0, p2.1 - p1.1);
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+    for (p, q) in [
+        (-dx, p1.0),
+        (dx, w - 1.0 - p1.0),
+        (-dy, p1.1),
+        (dy, h - 1.0 - p1.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64| (p1.0 + t * dx, p1.1 + t * dy);
+    Some((at(t0), at(t1)))
+}
+
+fn draw_line(rgba: &mut [u8], w: usize, h: usize, p1: (f64, f64), p2: (f64, f64), color: [u8; 4]) {
+    let ((x1, y1), (x2, y2)) = match clip(p1, p2, w as f64, h as f64) {
+        Some(clipped) => clipped,
+        None => return,
+    };
+    let steps = (x2 - x1).abs().max((y2 - y1).abs()).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        let t = i as f64 / steps as f64;
+        let x = (x1 + t * (x2 - x1)).round() as usize;
+        let y = (y1 + t * (y2 - y1)).round() as usize;
+        if x < w && y < h {
+            let offset = (y * w + x) * 4;
+            rgba[offset..offset + 4].copy_from_slice(&color);
+        }
+    }
+}
+
+//Draw the orbit of `c` over an RGBA image of `view`
+pub fn draw_orbit(
+    rgba: &mut [u8],
+    view: &ComplexBBox,
+    w: usize,
+    h: usize,
+    c: Complex<f64>,
+    bailout: &Bailout,
+) {
+    //screen coordinates without rounding, like complex_to_screen
+    let to_screen = |z: &Complex<f64>| {
+        (
+            (z.re - view.ll.re) * w as f64 / (view.ur.re - view.ll.re),
+            (z.im - view.ur.im) * h as f64 / (view.ll.im - view.ur.im),
+        )
+    };
+    let points: Vec<(f64, f64)> = calc_orbits(c, bailout).iter().map(to_screen).collect();
+    for (i, pair) in points.windows(2).enumerate() {
+        let color = if i == 0 { FIRST_COLOR } else { COLOR };
+        draw_line(rgba, w, h, pair[0], pair[1], color);
+    }
+}
+
+pub struct ZoomSequence {
+    pub start: ComplexBBox,
+    pub target: ComplexBBox,
+    pub frames: usize,
+    pub width: usize,
+    pub height: usize,
+    pub settings: RenderSettings,
+    pub base_iterations: u32, //for automatic iterations
+    pub orbit: Option<(Complex<f64>, Bailout)>, //drawn on every frame
+}
+
+impl ZoomSequence {
+    //Render every frame into `directory` as frame-00000.png, frame-00001.png...
+    //calling `progress` after each one.  Returns the paths written.
+    pub fn export(
+        &self,
+        directory: &Path,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<PathBuf>, String> {
+        std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+        let (w, h) = (self.width, self.height);
+        let _: i32 = w.try_into().map_err(|_| "frame too wide")?;
+        let _: i32 = h.try_into().map_err(|_| "frame too tall")?;
+
+        let mut paths = Vec::new();
+        for (i, view) in frames(&self.start, &self.target, self.frames).iter().enumerate() {
+            let mut settings = self.settings;
+            let mut rgba = render::render_rgba(view, w, h, &mut settings, self.base_iterations);
+            if let Some((c, bailout)) = &self.orbit {
+                draw_orbit(&mut rgba, view, w, h, *c, bailout);
+            }
+
+            let metadata = ViewMetadata {
+                view: *view,
+                iterations: settings.iterations,
+                saved_orbits: self.orbit.iter().map(|(c, _)| *c).collect(),
+            };
+            let png = screenshot::encode(w, h, &rgba, &metadata)?;
+            let path = directory.join(format!("frame-{:05}.png", i));
+            std::fs::write(&path, png).map_err(|e| format!("{}: {}", path.display(), e))?;
+            paths.push(path);
+            progress(i + 1, self.frames);
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> ComplexBBox {
+        let j = Complex { re: 0.0, im: 1.0 };
+        ComplexBBox {
+            ll: -1.5 - j,
+            ur: 0.5 + j,
+        }
+    }
+
+    fn assert_close(a: Complex<f64>, b: Complex<f64>) {
+        assert!((a - b).norm() < 1e-12, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn sequence_starts_and_ends_on_the_given_views() {
+        let target = home().zoom(Complex::new(-0.75, 0.1), 1e-4);
+        let views = frames(&home(), &target, 30);
+
+        assert_eq!(views.len(), 30);
+        assert_close(views[0].ll, home().ll);
+        assert_close(views[0].ur, home().ur);
+        assert_close(views[29].ll, target.ll);
+        assert_close(views[29].ur, target.ur);
+    }
+
+    #[test]
+    fn every_frame_zooms_by_the_same_factor_toward_one_point() {
+        let point = Complex::new(-0.75, 0.1);
+        let target = home().zoom(point, 1e-4);
+        let views = frames(&home(), &target, 5);
+
+        for pair in views.windows(2) {
+            let ratio = (pair[1].ur.re - pair[1].ll.re) / (pair[0].ur.re - pair[0].ll.re);
+            assert!((ratio - 0.1).abs() < 1e-9);
+            //zooming about `point` keeps it at the same place relative to the view
+            assert_close(pair[0].zoom(point, ratio).ll, pair[1].ll);
+        }
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_image() {
+        let clipped = clip((-10.0, 5.0), (20.0, 5.0), 10.0, 10.0).unwrap();
+        assert_eq!(clipped, ((0.0, 5.0), (9.0, 5.0)));
+        assert_eq!(clip((-1e12, -5.0), (1e12, -5.0), 10.0, 10.0), None);
+
+        let mut rgba = vec![0; 10 * 10 * 4];
+        draw_line(&mut rgba, 10, 10, (-1e12, 0.0), (1e12, 0.0), COLOR);
+        assert!(rgba[..40].chunks(4).all(|px| px == COLOR));
+        assert!(rgba[40..].iter().all(|b| *b == 0));
+    }
+}
@@ -0,0 +1,102 @@
+//The viewer driven over its control socket, under SDL's dummy video driver
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+use orbits::screenshot;
+
+struct Viewer(Child);
+
+impl Drop for Viewer {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[test]
+fn a_client_sets_the_view_saves_orbits_and_takes_screenshots() {
+    let dir = std::env::temp_dir().join(format!("orbits-control-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(&config, "").unwrap(); //no user settings
+    let port = free_port();
+    let mut viewer = Viewer(
+        Command::new(env!("CARGO_BIN_EXE_orbits"))
+            .current_dir(&dir) //where screenshots go
+            .env("SDL_VIDEODRIVER", "dummy")
+            .env("SDL_RENDER_DRIVER", "software")
+            .env("ORBITS_LOG", "warn")
+            .args(["--size", "300x200", "--config"])
+            .arg(&config)
+            .args(["--listen", &port.to_string()])
+            .spawn()
+            .unwrap(),
+    );
+
+    let started = Instant::now();
+    let stream = loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => break stream,
+            Err(e) if started.elapsed() > Duration::from_secs(20) => panic!("{}", e),
+            Err(_) => {
+                if let Some(status) = viewer.0.try_wait().unwrap() {
+                    panic!("the viewer exited with {}", status);
+                }
+                std::thread::sleep(Duration::from_millis(50))
+            }
+        }
+    };
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut id = 0;
+    let mut call = |method: &str, params: Value| -> Value {
+        id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(writer, "{}", request).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], id);
+        response
+    };
+
+    let state = call("set_view", json!({ "ll": [-2.0, -1.0], "ur": [1.0, 1.0] }))["result"].clone();
+    assert_eq!(state["view"]["ur"], json!([1.0, 1.0]));
+    call("set_iterations", json!({ "iterations": 120 }));
+    assert_eq!(
+        call("save_orbit", json!({ "c": [-0.1, 0.7] }))["result"]["saved"],
+        1
+    );
+    let orbit = call("get_orbit", json!({ "c": [0.5, 0.5] }))["result"]["orbit"].clone();
+    assert_eq!(orbit[1], json!([0.5, 0.5]));
+    let error = call("set_iterations", json!({ "iterations": "lots" }));
+    assert_eq!(error["error"]["code"], -32602);
+
+    let path = call("screenshot", json!({ "hud": false }))["result"]["path"].clone();
+    let png = std::fs::read(path.as_str().unwrap()).unwrap();
+    let metadata = screenshot::decode(&png).unwrap();
+    assert_eq!(metadata.iterations, 120);
+    assert_eq!(metadata.view.ll.re, -2.0);
+    assert_eq!(metadata.saved_orbits.len(), 1);
+
+    assert_eq!(
+        call("get_state", Value::Null)["result"],
+        json!({
+            "view": { "ll": [-2.0, -1.0], "ur": [1.0, 1.0] },
+            "iterations": 120,
+            "saved_orbits": [[-0.1, 0.7]],
+        })
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,210 @@
+//Golden-image regression tests: canonical views rendered headlessly through
+//every kernel and render strategy, compared with the reference images in
+//tests/golden/.  Each view has two references:
+//  <name>-iterations.png  escape times as 16 bit grayscale
+//  <name>.png             the colored background, as the viewer shows it
+//After a change that is meant to alter the output, rewrite them with
+//  ORBITS_REGENERATE_GOLDEN=1 cargo test --test golden
+//and look at the new images before committing them.
+use num::Complex;
+use std::path::PathBuf;
+
+use orbits::complex_bbox::ComplexBBox;
+use orbits::kernel::Kernel;
+use orbits::png_io;
+use orbits::render::{self, RenderSettings, Strategy};
+
+const WIDTH: usize = 120;
+const HEIGHT: usize = 80;
+
+//Fraction of pixels allowed to differ, escape times on the boundary move with
+//the last bits of precision (e.g. fused multiply-adds in the packed kernels)
+const TOLERANCE: f64 = 0.005;
+//and how far a color channel may be off before the pixel counts as different
+const COLOR_TOLERANCE: u8 = 2;
+
+struct View {
+    name: &'static str,
+    center: Complex<f64>,
+    width: f64, //height follows from the 3:2 image
+    iterations: u32,
+}
+
+const VIEWS: [View; 3] = [
+    View {
+        name: "full-set",
+        center: Complex { re: -0.75, im: 0.0 },
+        width: 3.0,
+        iterations: 100,
+    },
+    View {
+        name: "seahorse-valley",
+        center: Complex {
+            re: -0.7453,
+            im: 0.1127,
+        },
+        width: 0.015,
+        iterations: 500,
+    },
+    //period 5 minibrot on the real axis
+    View {
+        name: "deep-minibrot",
+        center: Complex {
+            re: -1.9854242530542052,
+            im: 0.0,
+        },
+        width: 3e-4,
+        iterations: 1500,
+    },
+];
+
+impl View {
+    fn bbox(&self) -> ComplexBBox {
+        let half = Complex::new(self.width, self.width * HEIGHT as f64 / WIDTH as f64) / 2.0;
+        ComplexBBox {
+            ll: self.center - half,
+            ur: self.center + half,
+        }
+    }
+}
+
+fn golden_path(file: String) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(file)
+}
+
+fn regenerate() -> bool {
+    std::env::var_os("ORBITS_REGENERATE_GOLDEN").is_some()
+}
+
+fn encode_gray16(counts: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, WIDTH as u32, HEIGHT as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        let data: Vec<u8> = counts
+            .iter()
+            .flat_map(|&c| (c.min(u16::MAX as u32) as u16).to_be_bytes())
+            .collect();
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&data).unwrap();
+    }
+    bytes
+}
+
+//image bytes, whatever the color type, with 16 bit samples kept big-endian
+fn decode(bytes: &[u8]) -> Vec<u8> {
+    let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+    let mut image = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut image).unwrap();
+    assert_eq!((info.width, info.height), (WIDTH as u32, HEIGHT as u32));
+    image.truncate(info.buffer_size());
+    image
+}
+
+//Compare with the reference, or write it when regenerating.  `differs` gets
+//the reference and the actual bytes of one pixel.
+fn check(file: String, actual: Vec<u8>, pixel_size: usize, differs: impl Fn(&[u8], &[u8]) -> bool) {
+    let path = golden_path(file);
+    if regenerate() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "{}: {}, create it with ORBITS_REGENERATE_GOLDEN=1",
+            path.display(),
+            e
+        )
+    });
+    let (expected, decoded) = (decode(&expected), decode(&actual));
+    let different = expected
+        .chunks(pixel_size)
+        .zip(decoded.chunks(pixel_size))
+        .filter(|(e, a)| differs(e, a))
+        .count();
+    if different as f64 > TOLERANCE * (WIDTH * HEIGHT) as f64 {
+        let saved = std::env::temp_dir().join(path.file_name().unwrap());
+        std::fs::write(&saved, &actual).unwrap();
+        panic!(
+            "{}: {} of {} pixels differ, the output is in {}",
+            path.display(),
+            different,
+            WIDTH * HEIGHT,
+            saved.display()
+        );
+    }
+}
+
+fn check_iterations(view: &View, counts: &[u32]) {
+    check(
+        format!("{}-iterations.png", view.name),
+        encode_gray16(counts),
+        2,
+        |e, a| e != a,
+    );
+}
+
+#[test]
+fn every_kernel_matches_the_reference_escape_times() {
+    for view in &VIEWS {
+        let bbox = view.bbox();
+        let kernels = if regenerate() {
+            vec![Kernel::Scalar] //the plainest code is the reference
+        } else {
+            Kernel::ALL.to_vec()
+        };
+        for kernel in kernels {
+            let counts = render::render_iterations(
+                &bbox,
+                WIDTH,
+                HEIGHT,
+                view.iterations,
+                kernel,
+                Strategy::BruteForce,
+            );
+            check_iterations(view, &counts);
+        }
+    }
+}
+
+#[test]
+fn subdivision_matches_the_reference_escape_times() {
+    if regenerate() {
+        return;
+    }
+    for view in &VIEWS {
+        let counts = render::render_iterations(
+            &view.bbox(),
+            WIDTH,
+            HEIGHT,
+            view.iterations,
+            Kernel::default(),
+            Strategy::Subdivide,
+        );
+        check_iterations(view, &counts);
+    }
+}
+
+#[test]
+fn coloring_matches_the_reference_images() {
+    for view in &VIEWS {
+        let mut settings = RenderSettings::new(view.iterations);
+        settings.kernel = Kernel::Scalar;
+        let rgba = render::render_rgba(&view.bbox(), WIDTH, HEIGHT, &mut settings, view.iterations);
+        check(
+            format!("{}.png", view.name),
+            png_io::encode_rgba(WIDTH, HEIGHT, &rgba).unwrap(),
+            4,
+            |e, a| {
+                e.iter()
+                    .zip(a)
+                    .any(|(e, a)| e.abs_diff(*a) > COLOR_TOLERANCE)
+            },
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//Recorded input replayed through the viewer under SDL's dummy video driver,
+//checking the view and saved orbits it ends up with.  The recordings are
+//written out here the way --record writes them.
+use num::Complex;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Command;
+
+use orbits::complex_bbox::ComplexBBox;
+
+const SIZE: i32 = 400;
+
+//the default view, -1.5-i to 0.5+i
+fn initial_view() -> ComplexBBox {
+    ComplexBBox {
+        ll: Complex::new(-1.5, -1.0),
+        ur: Complex::new(0.5, 1.0),
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("orbits-replay-{}-{}", std::process::id(), name))
+}
+
+//Play back `recording` and return the state the viewer exited with
+fn replay(name: &str, recording: &str) -> Value {
+    let (input, state, config) = (
+        temp_path(&format!("{}.jsonl", name)),
+        temp_path(&format!("{}-state.json", name)),
+        temp_path(&format!("{}-config.toml", name)),
+    );
+    std::fs::write(&input, recording).unwrap();
+    std::fs::write(&config, "").unwrap(); //no user settings
+    let size = format!("{}x{}", SIZE, SIZE);
+    let output = Command::new(env!("CARGO_BIN_EXE_orbits"))
+        .env("SDL_VIDEODRIVER", "dummy")
+        .env("SDL_RENDER_DRIVER", "software")
+        .env("ORBITS_LOG", "warn")
+        .args(["--size", &size, "--config"])
+        .arg(&config)
+        .arg("--replay")
+        .arg(&input)
+        .arg("--dump-state")
+        .arg(&state)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let text = std::fs::read_to_string(&state).unwrap();
+    for path in [input, state, config] {
+        let _ = std::fs::remove_file(path);
+    }
+    serde_json::from_str(&text).unwrap()
+}
+
+fn complex(pair: &Value) -> Complex<f64> {
+    Complex::new(pair[0].as_f64().unwrap(), pair[1].as_f64().unwrap())
+}
+
+fn assert_close(actual: Complex<f64>, expected: Complex<f64>) {
+    assert!(
+        (actual - expected).norm() < 1e-12,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+fn assert_view(state: &Value, expected: &ComplexBBox) {
+    assert_close(complex(&state["view"]["ll"]), expected.ll);
+    assert_close(complex(&state["view"]["ur"]), expected.ur);
+}
+
+fn saved_orbits(state: &Value) -> Vec<Complex<f64>> {
+    state["saved_orbits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(complex)
+        .collect()
+}
+
+#[test]
+fn dragging_pans_and_keys_save_orbits_under_the_mouse() {
+    let state = replay(
+        "pan",
+        r#"{"t":0,"event":"mouse_motion","x":200,"y":200,"xrel":0,"yrel":0,"left":false}
+{"t":10,"event":"mouse_down","button":"left","x":200,"y":200}
+{"t":20,"event":"mouse_motion","x":220,"y":200,"xrel":20,"yrel":0,"left":true}
+{"t":30,"event":"mouse_motion","x":250,"y":200,"xrel":30,"yrel":0,"left":true}
+{"t":40,"event":"mouse_up","button":"left","x":250,"y":200}
+{"t":50,"event":"key_down","key":"Space","shift":false}
+{"t":60,"event":"key_up","key":"Space","shift":false}
+{"t":70,"event":"mouse_down","button":"right","x":100,"y":300}
+{"t":80,"event":"quit"}
+"#,
+    );
+    let shift = initial_view().complex_deltas(SIZE, SIZE, 50, 0);
+    let view = ComplexBBox {
+        ll: initial_view().ll - shift,
+        ur: initial_view().ur - shift,
+    };
+    assert_view(&state, &view);
+    assert_close(view.ll, Complex::new(-1.75, -1.0));
+    let saved = saved_orbits(&state);
+    assert_eq!(saved.len(), 2);
+    assert_close(saved[0], view.screen_to_complex(250, 200, SIZE, SIZE));
+    assert_close(saved[1], view.screen_to_complex(100, 300, SIZE, SIZE));
+    assert_eq!(state["iterations"], 50);
+}
+
+#[test]
+fn the_wheel_zooms_about_the_mouse() {
+    let state = replay(
+        "wheel",
+        r#"{"t":0,"event":"mouse_motion","x":100,"y":300,"xrel":0,"yrel":0,"left":false}
+{"t":10,"event":"mouse_wheel","y":1}
+{"t":20,"event":"mouse_wheel","y":1}
+{"t":30,"event":"mouse_motion","x":300,"y":100,"xrel":200,"yrel":-200,"left":false}
+{"t":40,"event":"mouse_wheel","y":-1}
+{"t":50,"event":"quit"}
+"#,
+    );
+    let mut view = initial_view();
+    for (x, y, scale) in [(100, 300, 0.5), (100, 300, 0.5), (300, 100, 2.0)] {
+        view = view.zoom(view.screen_to_complex(x, y, SIZE, SIZE), scale);
+    }
+    assert_view(&state, &view);
+    assert_eq!(saved_orbits(&state), vec![]);
+}
+
+#[test]
+fn pinching_zooms_when_the_fingers_lift() {
+    let state = replay(
+        "pinch",
+        r#"{"t":0,"event":"multi_gesture","x":0.5,"y":0.5,"d_dist":0.025,"num_fingers":2}
+{"t":10,"event":"finger_up"}
+{"t":20,"event":"quit"}
+"#,
+    );
+    //the background is stretched from the middle (1 - 10 d_dist) of it
+    let width = (SIZE as f32 * (1.0 - 10.0 * 0.025)) as u32;
+    assert_eq!(width, 300);
+    let view = initial_view().zoom(
+        initial_view().screen_to_complex(200, 200, SIZE, SIZE),
+        width as f64 / SIZE as f64,
+    );
+    assert_view(&state, &view);
+}
@@ -0,0 +1,82 @@
+//Headless --script runs, which use SDL's dummy video driver by themselves
+use serde_json::{json, Value};
+use std::process::{Command, Output};
+
+use orbits::screenshot::{self, ViewMetadata};
+
+//The run's output, the state it ended in and the metadata of the screenshots
+//it took, in order
+fn run_script(name: &str, source: &str) -> (Output, Option<Value>, Vec<ViewMetadata>) {
+    let dir = std::env::temp_dir().join(format!("orbits-script-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (script, state, config) = (
+        dir.join("script.rhai"),
+        dir.join("state.json"),
+        dir.join("config.toml"),
+    );
+    std::fs::write(&script, source).unwrap();
+    std::fs::write(&config, "").unwrap(); //no user settings
+    let output = Command::new(env!("CARGO_BIN_EXE_orbits"))
+        .current_dir(&dir) //where screenshots go
+        .env_remove("SDL_VIDEODRIVER")
+        .env("ORBITS_LOG", "warn")
+        .args(["--size", "300x200", "--config"])
+        .arg(&config)
+        .arg("--script")
+        .arg(&script)
+        .arg("--dump-state")
+        .arg(&state)
+        .output()
+        .unwrap();
+    let state = std::fs::read_to_string(&state)
+        .ok()
+        .map(|text| serde_json::from_str(&text).unwrap());
+    let mut pngs: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some("png".as_ref()))
+        .collect();
+    pngs.sort_by_key(|path| std::fs::metadata(path).unwrap().modified().unwrap());
+    let screenshots = pngs
+        .iter()
+        .map(|path| screenshot::decode(&std::fs::read(path).unwrap()).unwrap())
+        .collect();
+    let _ = std::fs::remove_dir_all(&dir);
+    (output, state, screenshots)
+}
+
+#[test]
+fn a_script_saves_orbits_around_a_circle_and_screenshots_them() {
+    let (output, state, screenshots) = run_script(
+        "circle",
+        r#"
+        set_view(-2, -1, 1, 1);
+        set_iterations(120);
+        for i in 0..3 {
+            let t = 2.0 * PI() * i / 3.0;
+            save_orbit(-0.75 + 0.25 * cos(t), 0.25 * sin(t));
+            print(screenshot(false));
+        }
+        "#,
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    let state = state.unwrap();
+    assert_eq!(state["view"]["ll"], json!([-2.0, -1.0]));
+    assert_eq!(state["iterations"], 120);
+    assert_eq!(state["saved_orbits"].as_array().unwrap().len(), 3);
+    assert_eq!(state["saved_orbits"][0], json!([-0.5, 0.0]));
+    //each taken once that time round the loop had saved its orbit
+    let saved: Vec<usize> = screenshots.iter().map(|m| m.saved_orbits.len()).collect();
+    assert_eq!(saved, [1, 2, 3]);
+    assert!(screenshots.iter().all(|m| m.iterations == 120));
+}
+
+#[test]
+fn a_failing_script_fails_the_run() {
+    let (output, state, _) = run_script("failing", "save_orbit(0, 0);\nscreenshot(\"yes\");");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("script error"), "{}", stderr);
+    assert_eq!(state.unwrap()["saved_orbits"], json!([[0.0, 0.0]]));
+}